@@ -0,0 +1,51 @@
+// Throughput della verifica firme: sequenziale, in parallelo (cache fredda) e con cache calda.
+// Uso: cargo bench --bench signature_verification [-- <numero transazioni> <schema>]
+use adamas_core::signature::{self, Algorithm};
+use adamas_core::transaction::Transaction;
+use adamas_core::verifier;
+use adamas_core::wallet::Wallet;
+use std::env;
+use std::time::{Duration, Instant};
+
+const DEFAULT_TXS: usize = 500;
+
+fn report(label: &str, count: usize, elapsed: Duration) {
+    let per_second = count as f64 / elapsed.as_secs_f64().max(f64::EPSILON);
+    println!("{:<28} {:>8.1} ms  {:>10.0} tx/s", label, elapsed.as_secs_f64() * 1000.0, per_second);
+}
+
+fn main() {
+    // `cargo bench` aggiunge "--bench": si tengono solo gli argomenti senza trattini
+    let args: Vec<String> = env::args().skip(1).filter(|arg| !arg.starts_with("--")).collect();
+    let count = args.first().and_then(|n| n.parse().ok()).unwrap_or(DEFAULT_TXS);
+    let algorithm = args.get(1)
+        .map(|name| Algorithm::from_name(name).unwrap_or_else(|| panic!("unknown signature scheme '{}'", name)))
+        .unwrap_or(signature::DEFAULT_ALGORITHM);
+
+    println!("⏱️ {} transazioni firmate con {}", count, algorithm.name());
+    let wallets: Vec<Wallet> = (0..16).map(|_| Wallet::generate(algorithm)).collect();
+    let receiver = wallets[0].address();
+    let txs: Vec<Transaction> = (0..count)
+        .map(|i| Transaction::new(&wallets[i % wallets.len()], receiver.clone(), 1, 1, i as u64))
+        .collect();
+    // Prima spesa: la chiave viaggia nella transazione, nessuna chiave registrata
+    let batch: Vec<(&Transaction, Option<&str>)> = txs.iter().map(|tx| (tx, None)).collect();
+
+    let start = Instant::now();
+    let sequential = txs.iter().filter(|tx| tx.verify(None)).count();
+    report("sequenziale", count, start.elapsed());
+
+    let start = Instant::now();
+    let parallel = verifier::verify_batch(&batch).into_iter().filter(|valid| *valid).count();
+    report("parallelo (cache fredda)", count, start.elapsed());
+
+    let start = Instant::now();
+    let cached = verifier::verify_batch(&batch).into_iter().filter(|valid| *valid).count();
+    report("parallelo (cache calda)", count, start.elapsed());
+
+    let start = Instant::now();
+    let single = txs.iter().filter(|tx| verifier::verify(tx, None)).count();
+    report("singola (cache calda)", count, start.elapsed());
+
+    assert!(sequential == count && parallel == count && cached == count && single == count, "some signatures failed to verify");
+}
@@ -0,0 +1,110 @@
+// Indirizzi brevi: "adm" + hex(20 byte di sha3(chiave pubblica) + 4 byte di checksum).
+// 51 caratteri invece dei ~5 KB di hex della chiave Dilithium-5.
+use crate::multisig::{MultisigPolicy, MULTISIG_PREFIX};
+use crate::signature;
+use sha3::{Digest, Sha3_256};
+
+pub const ADDRESS_PREFIX: &str = "adm";
+const HASH_BYTES: usize = 20;
+const CHECKSUM_BYTES: usize = 4;
+
+fn checksum(payload: &[u8]) -> [u8; CHECKSUM_BYTES] {
+    let mut hasher = Sha3_256::new();
+    hasher.update(ADDRESS_PREFIX.as_bytes());
+    hasher.update(payload);
+    let digest = hasher.finalize();
+    let mut out = [0u8; CHECKSUM_BYTES];
+    out.copy_from_slice(&digest[..CHECKSUM_BYTES]);
+    out
+}
+
+// Indirizzo a partire dalla chiave pubblica in hex (stringa vuota se l'hex non è valido)
+pub fn from_public_key(public_key_hex: &str) -> String {
+    match hex::decode(public_key_hex) {
+        Ok(bytes) => from_public_key_bytes(&bytes),
+        Err(_) => String::new(),
+    }
+}
+
+pub fn from_public_key_bytes(public_key: &[u8]) -> String {
+    let digest = Sha3_256::digest(public_key);
+    let payload = &digest[..HASH_BYTES];
+    let mut data = payload.to_vec();
+    data.extend_from_slice(&checksum(payload));
+    format!("{}{}", ADDRESS_PREFIX, hex::encode(data))
+}
+
+// Indirizzo di una chiave di conto: chiave Dilithium in hex oppure politica multisig (vedi multisig.rs)
+pub fn from_account_key(key: &str) -> String {
+    if key.starts_with(MULTISIG_PREFIX) {
+        from_public_key_bytes(key.as_bytes())
+    } else {
+        from_public_key(key)
+    }
+}
+
+// Indirizzo di un contratto: deriva da chi lo pubblica e dal nonce, nessuna chiave può spenderne i fondi
+pub fn contract_address(deployer: &str, nonce: u64) -> String {
+    from_public_key_bytes(format!("contract/{}/{}", deployer, nonce).as_bytes())
+}
+
+// Chiave che può autorizzare un conto: chiave pubblica di uno schema supportato o politica multisig
+pub fn is_valid_account_key(key: &str) -> bool {
+    MultisigPolicy::decode(key).is_some() || signature::algorithm_of(key).is_some()
+}
+
+// Controlla prefisso, lunghezza e checksum (un carattere sbagliato viene quasi sempre scoperto)
+pub fn is_valid(address: &str) -> bool {
+    let body = match address.strip_prefix(ADDRESS_PREFIX) {
+        Some(body) => body,
+        None => return false,
+    };
+    if body.len() != (HASH_BYTES + CHECKSUM_BYTES) * 2 || body.chars().any(|c| c.is_ascii_uppercase()) {
+        return false;
+    }
+    match hex::decode(body) {
+        Ok(data) => data[HASH_BYTES..] == checksum(&data[..HASH_BYTES]),
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> String {
+        from_public_key_bytes(b"adamas test key")
+    }
+
+    #[test]
+    fn derived_address_is_valid() {
+        let address = sample();
+        assert_eq!(address.len(), ADDRESS_PREFIX.len() + (HASH_BYTES + CHECKSUM_BYTES) * 2);
+        assert!(is_valid(&address));
+        assert_eq!(from_public_key(&hex::encode(b"adamas test key")), address);
+        assert_eq!(from_public_key("not hex"), "");
+    }
+
+    #[test]
+    fn checksum_catches_a_flipped_character() {
+        let address = sample();
+        for position in ADDRESS_PREFIX.len()..address.len() {
+            let mut chars: Vec<char> = address.chars().collect();
+            chars[position] = if chars[position] == '0' { '1' } else { '0' };
+            let flipped: String = chars.into_iter().collect();
+            assert!(!is_valid(&flipped), "flip at {} accepted", position);
+        }
+    }
+
+    #[test]
+    fn rejects_wrong_prefix_length_or_case() {
+        let address = sample();
+        let body = &address[ADDRESS_PREFIX.len()..];
+        assert!(!is_valid(&format!("xyz{}", body)));
+        assert!(!is_valid(body));
+        assert!(!is_valid(&address[..address.len() - 2]));
+        assert!(!is_valid(&format!("{}00", address)));
+        assert!(!is_valid(&address.to_uppercase()));
+        assert!(!is_valid(""));
+    }
+}
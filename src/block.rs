@@ -1,19 +1,33 @@
+use crate::signature;
+use crate::signer;
+use crate::supply::Allocation;
+use crate::transaction::Transaction;
 use serde::{Serialize, Deserialize};
-use sha2::{Sha256, Digest};
+use sha3::{Sha3_512, Digest};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Block {
     pub index: u64,
     pub timestamp: u128,
+    pub data: String,
+    pub transactions: Vec<Transaction>,
     pub previous_hash: String,
     pub hash: String,
-    pub data: String, // Per ora stringa, presto sarà Vec<Transaction>
-    pub validator: String,
+    pub node_id: String,
+    pub validator: String, // Indirizzo che incassa commissioni e ricompensa del blocco
+    pub reward: u64,       // ADM coniati per il produttore (limitati dal calendario di emissione)
+    pub allocations: Vec<Allocation>, // Solo nel blocco di genesi
+    pub state_root: String, // Radice dello Sparse Merkle Tree dello stato DOPO questo blocco
+    // Chiave del produttore (fa parte dell'hash) e sua firma sull'hash; vuote nei blocchi non firmati
+    #[serde(default)]
+    pub producer_key: String,
+    #[serde(default)]
+    pub signature: String,
 }
 
 impl Block {
-    pub fn new(index: u64, previous_hash: String, data: String, validator: String) -> Block {
+    pub fn new(index: u64, previous_hash: String, data: String, transactions: Vec<Transaction>, node_id: String, validator: String, reward: u64) -> Block {
         let timestamp = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .expect("Time error")
@@ -22,20 +36,69 @@ impl Block {
         let mut block = Block {
             index,
             timestamp,
+            data,
+            transactions,
             previous_hash,
             hash: String::new(),
-            data,
+            node_id,
             validator,
+            reward,
+            allocations: Vec::new(),
+            state_root: String::new(),
+            producer_key: String::new(),
+            signature: String::new(),
         };
         block.hash = block.calculate_hash();
         block
     }
 
+    pub fn genesis(allocations: Vec<Allocation>) -> Block {
+        Block {
+            index: 0,
+            timestamp: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis(),
+            data: "GENESIS BLOCK".to_string(),
+            transactions: Vec::new(),
+            previous_hash: "0".to_string(),
+            hash: "00000000000000000000".to_string(),
+            node_id: "SYSTEM".to_string(),
+            validator: String::new(),
+            reward: 0,
+            allocations,
+            state_root: String::new(),
+            producer_key: String::new(),
+            signature: String::new(),
+        }
+    }
+
     pub fn calculate_hash(&self) -> String {
-        let input = format!("{}{}{}{}{}", 
-            self.index, self.timestamp, self.previous_hash, self.data, self.validator);
-        let mut hasher = Sha256::new();
+        let input = format!("{}{}{}{}{}{}{}{}",
+            self.index, self.timestamp, self.data, self.previous_hash, self.validator, self.reward, self.state_root, self.producer_key);
+        let mut hasher = Sha3_512::new();
         hasher.update(input);
-        hex::encode(hasher.finalize())
+        // Il nodo d'origine finisce nella cronologia dei conti: con la lunghezza davanti nessuno lo riscrive in transito
+        hasher.update((self.node_id.len() as u64).to_be_bytes());
+        hasher.update(self.node_id.as_bytes());
+        for allocation in &self.allocations {
+            hasher.update(format!("{}{}", allocation.address, allocation.amount));
+        }
+        // L'ID copre tutto il contenuto firmato della transazione: un campo aggiunto al payload entra da solo nell'hash
+        for tx in &self.transactions {
+            hasher.update(format!("{}|{}|{}", tx.id(), tx.public_key, tx.signature));
+            for partial in &tx.signatures {
+                hasher.update(format!("|{}:{}", partial.signer, partial.signature));
+            }
+            hasher.update(";");
+        }
+        format!("{:x}", hasher.finalize())
     }
-}
\ No newline at end of file
+
+    pub fn is_signed(&self) -> bool {
+        !self.producer_key.is_empty() || !self.signature.is_empty()
+    }
+
+    // La firma copre indice e hash, e l'hash copre la chiave del produttore
+    pub fn verify_signature(&self) -> bool {
+        self.hash == self.calculate_hash()
+            && signature::verify(&signer::block_message(self.index, &self.hash), &self.signature, &self.producer_key)
+    }
+}
@@ -0,0 +1,244 @@
+use crate::address;
+use crate::block::Block;
+use crate::database::BlockchainDB;
+use crate::receipts::{Receipt, TxStatus};
+use crate::state::AccountState;
+use crate::supply;
+use crate::transaction::Transaction;
+use std::collections::VecDeque;
+use std::error::Error;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Quanti blocchi recenti teniamo in RAM (il resto vive solo su disco)
+pub const RECENT_BLOCKS_CACHE: usize = 100;
+
+// Scarto massimo tra l'orologio del produttore e il nostro
+pub const MAX_FUTURE_DRIFT_MS: u128 = 2 * 60 * 1000;
+
+pub struct Blockchain {
+    db: BlockchainDB,
+    state: AccountState,
+    // Cache limitata degli ultimi blocchi, l'ultimo elemento è la punta della catena
+    recent: VecDeque<Block>,
+}
+
+impl Blockchain {
+    // Se il database è vuoto crea il blocco di genesi con le allocazioni di supply.rs
+    pub fn new(db: BlockchainDB) -> Result<Self, Box<dyn Error>> {
+        let state = AccountState::open(&db)?;
+        let mut chain = Blockchain { db, state, recent: VecDeque::with_capacity(RECENT_BLOCKS_CACHE) };
+
+        let genesis_allocations = supply::genesis_allocations();
+        match chain.db.load_tip()? {
+            Some(tip) => {
+                // Un database creato con altre allocazioni appartiene a un'altra catena
+                let genesis = chain.db.load_block_at(0)?.ok_or("database without a genesis block")?;
+                if genesis.allocations != genesis_allocations {
+                    return Err("the genesis block on disk has different allocations than this build: the database belongs to another chain".into());
+                }
+                // Stato e blocchi si scrivono insieme: se non coincidono il database è danneggiato
+                let state_root = chain.state.root()?;
+                if state_root != tip.state_root {
+                    return Err(format!("the account state (root {}) does not match block #{} (root {}): the database is damaged", state_root, tip.index, tip.state_root).into());
+                }
+                let first = (tip.index + 1).saturating_sub(RECENT_BLOCKS_CACHE as u64);
+                for index in first..tip.index {
+                    if let Some(block) = chain.db.load_block_at(index)? {
+                        chain.recent.push_back(block);
+                    }
+                }
+                println!("💾 CHAIN LOADED: {} blocks on disk", tip.index + 1);
+                chain.recent.push_back(tip);
+            }
+            None => chain.create_genesis_block(genesis_allocations)?,
+        }
+        Ok(chain)
+    }
+
+    fn create_genesis_block(&mut self, allocations: Vec<supply::Allocation>) -> Result<(), Box<dyn Error>> {
+        let mut genesis = Block::genesis(allocations);
+        genesis.state_root = self.state.state_root_after(&genesis)?;
+        self.push(genesis)
+    }
+
+    // Applica le transazioni e salva su disco (una sola transazione), poi aggiorna la cache scartando i blocchi più vecchi
+    fn push(&mut self, block: Block) -> Result<(), Box<dyn Error>> {
+        self.state.apply_block(&block, &self.db)?;
+        if self.recent.len() == RECENT_BLOCKS_CACHE {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(block);
+        Ok(())
+    }
+
+    pub fn last_block(&self) -> &Block {
+        self.recent.back().expect("Chain without genesis")
+    }
+
+    pub fn height(&self) -> u64 {
+        self.last_block().index
+    }
+
+    pub fn state(&self) -> &AccountState {
+        &self.state
+    }
+
+    pub fn recent_blocks(&self) -> &VecDeque<Block> {
+        &self.recent
+    }
+
+    // Cerca prima in cache, poi su disco
+    pub fn get_block(&self, index: u64) -> Result<Option<Block>, Box<dyn Error>> {
+        if let Some(block) = self.recent.iter().find(|b| b.index == index) {
+            return Ok(Some(block.clone()));
+        }
+        self.db.load_block_at(index)
+    }
+
+    // Dove è finita una transazione (None se non è in nessun blocco)
+    pub fn receipt(&self, tx_id: &str) -> Result<Option<Receipt>, Box<dyn Error>> {
+        self.db.load_receipt(tx_id)
+    }
+
+    // Stato di una transazione già in un blocco (None se la catena non la conosce)
+    pub fn tx_status(&self, tx_id: &str) -> Result<Option<TxStatus>, Box<dyn Error>> {
+        Ok(self.receipt(tx_id)?.map(|receipt| TxStatus::from_receipt(&receipt, self.height())))
+    }
+
+    // Blocco sopra la punta da firmare fuori dal lock della catena, poi `commit_block`; le ricompense vanno a `producer_key`
+    pub fn prepare_block(&self, data: String, transactions: Vec<Transaction>, node_origin: String, producer_key: &str) -> Result<Block, Box<dyn Error>> {
+        let previous_block = self.last_block();
+        if let Some(signed) = self.db.load_signed_block()? {
+            if signed.previous_hash == previous_block.hash && signed.producer_key == producer_key {
+                println!("♻️ BLOCK #{} già firmato ma non aggiunto: si ripropone lo stesso", signed.index);
+                return Ok(signed);
+            }
+        }
+        let index = previous_block.index + 1;
+        let validator = address::from_public_key(producer_key);
+        let reward = self.state.next_block_reward(index)?;
+        let mut new_block = Block::new(index, previous_block.hash.clone(), data, Vec::new(), node_origin, validator, reward);
+        // Le finestre di validità dipendono da altezza e timestamp: le transazioni si scelgono a blocco creato
+        new_block.transactions = self.state.select_transactions(transactions, &new_block);
+        // La radice di stato fa parte dell'header: va calcolata prima dell'hash definitivo
+        new_block.state_root = self.state.state_root_after(&new_block)?;
+        new_block.producer_key = producer_key.to_string();
+        new_block.hash = new_block.calculate_hash();
+        Ok(new_block)
+    }
+
+    // Aggiunge un blocco preparato e firmato da noi, se nel frattempo la punta non è cambiata
+    pub fn commit_block(&mut self, new_block: Block) -> Result<(), Box<dyn Error>> {
+        if new_block.previous_hash != self.last_block().hash {
+            return Err(format!("block #{} no longer extends our tip: the chain moved while it was being signed", new_block.index).into());
+        }
+        if !new_block.verify_signature() {
+            return Err(format!("block #{} has an invalid producer signature", new_block.index).into());
+        }
+        self.db.save_signed_block(&new_block)?;
+        let (index, data, count) = (new_block.index, new_block.data.clone(), new_block.transactions.len());
+        self.push(new_block)?;
+        self.db.clear_signed_block()?;
+        println!("✅ BLOCK #{} MINED: {} ({} tx)", index, data, count);
+        Ok(())
+    }
+
+    // Ok(true) se il blocco è stato aggiunto, Ok(false) se non si può ancora valutare (vecchio, già noto o
+    // oltre un buco di blocchi mancanti): in quel caso non è stato controllato e non va inoltrato
+    pub fn receive_block(&mut self, remote_block: Block) -> Result<bool, Box<dyn Error>> {
+        let tip = self.last_block();
+        if remote_block.index <= tip.index {
+            return Ok(false);
+        }
+        if remote_block.index > tip.index + 1 {
+            println!("📥 SYNC: Block #{} is ahead of our tip #{}: missing blocks", remote_block.index, tip.index);
+            return Ok(false);
+        }
+        if remote_block.previous_hash != tip.hash {
+            return Err(format!("block #{} does not extend our tip {}", remote_block.index, tip.hash).into());
+        }
+        if remote_block.hash != remote_block.calculate_hash() {
+            return Err(format!("block #{} has a hash that does not match its content", remote_block.index).into());
+        }
+        // Ogni blocco è firmato da chi lo produce, e le ricompense vanno all'indirizzo di quella chiave
+        if !remote_block.is_signed() {
+            return Err(format!("block #{} is not signed by its producer", remote_block.index).into());
+        }
+        if address::from_public_key(&remote_block.producer_key) != remote_block.validator {
+            return Err(format!("block #{} pays {} but is signed by another key", remote_block.index, remote_block.validator).into());
+        }
+        if !remote_block.verify_signature() {
+            return Err(format!("block #{} has an invalid producer signature", remote_block.index).into());
+        }
+        // Il timestamp decide le finestre di validità a tempo: né prima della punta né troppo nel futuro
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis();
+        if remote_block.timestamp < tip.timestamp || remote_block.timestamp > now + MAX_FUTURE_DRIFT_MS {
+            return Err(format!("block #{} has an out of range timestamp {}", remote_block.index, remote_block.timestamp).into());
+        }
+        println!("📥 SYNC: Block #{} received from {}", remote_block.index, remote_block.node_id);
+        self.push(remote_block)?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signer::Signer;
+    use crate::wallet::Wallet;
+
+    #[test]
+    fn signed_block_is_resubmitted_after_a_failed_commit() {
+        let chain = Blockchain::new(BlockchainDB::temporary().unwrap()).unwrap();
+        let producer = Wallet::new();
+        let mut block = chain.prepare_block("first".to_string(), Vec::new(), "TEST".to_string(), &producer.public_key).unwrap();
+        block.signature = producer.sign_block(block.index, &block.hash).unwrap();
+        // Come dopo un `commit_block` fallito: il blocco firmato resta su disco, la punta no
+        chain.db.save_signed_block(&block).unwrap();
+
+        let again = chain.prepare_block("second".to_string(), Vec::new(), "TEST".to_string(), &producer.public_key).unwrap();
+        assert_eq!(again.hash, block.hash);
+        // Un'altra chiave prepara il suo blocco
+        let other = Wallet::new();
+        let fresh = chain.prepare_block("second".to_string(), Vec::new(), "TEST".to_string(), &other.public_key).unwrap();
+        assert_ne!(fresh.hash, block.hash);
+
+        let mut chain = chain;
+        chain.commit_block(again).unwrap();
+        assert_eq!(chain.height(), 1);
+        assert!(chain.db.load_signed_block().unwrap().is_none());
+    }
+
+    // Prepara, firma e aggiunge un blocco del produttore
+    fn mine(chain: &mut Blockchain, producer: &Wallet, transactions: Vec<Transaction>) -> Block {
+        let mut block = chain.prepare_block("test".to_string(), transactions, "TEST".to_string(), &producer.public_key).unwrap();
+        block.signature = producer.sign_block(block.index, &block.hash).unwrap();
+        chain.commit_block(block.clone()).unwrap();
+        block
+    }
+
+    #[test]
+    fn receipt_goes_from_pending_to_finalized() {
+        let mut chain = Blockchain::new(BlockchainDB::temporary().unwrap()).unwrap();
+        let producer = Wallet::new();
+        mine(&mut chain, &producer, Vec::new());
+
+        let tx = Transaction::new(&producer, Wallet::new().address(), 100, 1_000, 0);
+        let mut mempool = crate::mempool::Mempool::new(0, Default::default());
+        let balance = chain.state().balance(&producer.address()).unwrap();
+        mempool.add_transaction(tx.clone(), 0, None, balance, chain.height() + 1).unwrap();
+        assert_eq!(mempool.status(&tx.id()), Some(TxStatus::Pending));
+        assert_eq!(chain.tx_status(&tx.id()).unwrap(), None);
+
+        let block = mine(&mut chain, &producer, vec![tx.clone()]);
+        assert_eq!(block.transactions.len(), 1);
+        let receipt = chain.receipt(&tx.id()).unwrap().unwrap();
+        assert_eq!((receipt.block_index, receipt.block_hash.as_str(), receipt.position), (block.index, block.hash.as_str(), 0));
+        assert!(matches!(chain.tx_status(&tx.id()).unwrap(), Some(TxStatus::Included { confirmations: 1, .. })));
+
+        for _ in 1..crate::receipts::FINALITY_DEPTH {
+            mine(&mut chain, &producer, Vec::new());
+        }
+        assert!(matches!(chain.tx_status(&tx.id()).unwrap(), Some(TxStatus::Finalized { confirmations: crate::receipts::FINALITY_DEPTH, .. })));
+    }
+}
@@ -0,0 +1,115 @@
+// Record di certificazione logistica: i dati di un lotto firmati dal mittente della transazione
+// (vedi TransactionKind::CertifyRecord). Lo schema è validato dal nodo prima di finire on-chain.
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::error::Error;
+
+pub const MAX_FIELD_BYTES: usize = 256;
+pub const MAX_ATTACHMENTS: usize = 16;
+pub const MAX_METADATA_ENTRIES: usize = 32;
+pub const MAX_METADATA_KEY_BYTES: usize = 64;
+const ATTACHMENT_HASH_HEX: usize = 64; // SHA3-256
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CertificationRecord {
+    pub product: String,  // Codice prodotto / SKU
+    pub batch_id: String,
+    pub operator: String,
+    #[serde(default)]
+    pub location: String,
+    #[serde(default)]
+    pub quantity: u64, // 0 = non indicata
+    #[serde(default)]
+    pub unit: String, // Es. "kg", "pz"
+    pub timestamp: u64, // Secondi UNIX dell'evento certificato (non del blocco)
+    // SHA3-256 (hex) dei documenti allegati: i file restano al cliente, la catena ne prova l'esistenza
+    #[serde(default)]
+    pub attachments: Vec<String>,
+    // Campi liberi del cliente; la BTreeMap tiene le chiavi in ordine, quindi la codifica firmata è stabile
+    #[serde(default)]
+    pub metadata: BTreeMap<String, String>,
+}
+
+pub(crate) fn check_text(name: &str, value: &str, required: bool) -> Result<(), Box<dyn Error>> {
+    if required && value.trim().is_empty() {
+        return Err(format!("record {} is required", name).into());
+    }
+    if value.len() > MAX_FIELD_BYTES {
+        return Err(format!("record {} exceeds {} bytes", name, MAX_FIELD_BYTES).into());
+    }
+    // Niente caratteri di controllo: i record finiscono in log, dashboard e QR code
+    if value.chars().any(char::is_control) {
+        return Err(format!("record {} contains control characters", name).into());
+    }
+    Ok(())
+}
+
+// Impronta SHA3-256 in hex minuscolo (allegati dei record, documenti notarizzati)
+pub(crate) fn is_hash_hex(hash: &str) -> bool {
+    hash.len() == ATTACHMENT_HASH_HEX && hash.chars().all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c))
+}
+
+pub(crate) fn check_metadata(metadata: &BTreeMap<String, String>) -> Result<(), Box<dyn Error>> {
+    if metadata.len() > MAX_METADATA_ENTRIES {
+        return Err(format!("a record can have at most {} metadata entries", MAX_METADATA_ENTRIES).into());
+    }
+    for (key, value) in metadata {
+        let valid_key = !key.is_empty()
+            && key.len() <= MAX_METADATA_KEY_BYTES
+            && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.');
+        if !valid_key {
+            return Err(format!("metadata key '{}' must be 1 to {} characters among [A-Za-z0-9_.-]", key, MAX_METADATA_KEY_BYTES).into());
+        }
+        check_text(&format!("metadata '{}'", key), value, false)?;
+    }
+    Ok(())
+}
+
+impl CertificationRecord {
+    pub fn new(product: String, batch_id: String, operator: String, timestamp: u64) -> Self {
+        CertificationRecord {
+            product,
+            batch_id,
+            operator,
+            location: String::new(),
+            quantity: 0,
+            unit: String::new(),
+            timestamp,
+            attachments: Vec::new(),
+            metadata: BTreeMap::new(),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), Box<dyn Error>> {
+        check_text("product", &self.product, true)?;
+        check_text("batch_id", &self.batch_id, true)?;
+        check_text("operator", &self.operator, true)?;
+        check_text("location", &self.location, false)?;
+        check_text("unit", &self.unit, false)?;
+        if self.unit.is_empty() != (self.quantity == 0) {
+            return Err("record quantity and unit must be given together".into());
+        }
+        if self.timestamp == 0 {
+            return Err("record timestamp is required".into());
+        }
+
+        if self.attachments.len() > MAX_ATTACHMENTS {
+            return Err(format!("a record can have at most {} attachments", MAX_ATTACHMENTS).into());
+        }
+        for (i, hash) in self.attachments.iter().enumerate() {
+            if !is_hash_hex(hash) {
+                return Err(format!("attachment {} is not a lowercase hex SHA3-256 hash", hash).into());
+            }
+            if self.attachments[..i].contains(hash) {
+                return Err(format!("attachment {} is listed twice", hash).into());
+            }
+        }
+
+        check_metadata(&self.metadata)
+    }
+
+    // Riga leggibile per log e dashboard (Block.data)
+    pub fn summary(&self) -> String {
+        format!("{} | {} | {}", self.product, self.batch_id, self.operator)
+    }
+}
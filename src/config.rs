@@ -1,3 +1,4 @@
+use crate::mempool::MempoolLimits;
 use serde::{Deserialize, Serialize};
 use std::fs;
 
@@ -8,6 +9,14 @@ pub struct NodeConfig {
     pub db_path: String,         // Es: "./db_rossi"
     pub node_role: String,       // Es: "Master Node" o "Warehouse Node"
     pub server_port: u16,        // Porta P2P (es. 0 per automatica)
+    #[serde(default)]
+    pub min_relay_fee: u64,      // Commissione minima ogni 1000 byte per accettare una transazione
+    #[serde(default)]
+    pub mempool: MempoolLimits, // Capienza della mempool (numero, byte, per mittente)
+    #[serde(default)]
+    pub reward_address: String, // Se indicato deve coincidere con l'indirizzo del firmatario, che incassa commissioni e ricompense
+    #[serde(default)]
+    pub signer_socket: String, // Socket di adamas-signer per firmare i blocchi (vuoto = il nodo non produce blocchi)
 }
 
 impl NodeConfig {
@@ -1,22 +1,54 @@
-use sled::Db;
+use sled::{Batch, Db, Tree};
 use crate::block::Block;
+use crate::receipts::Receipt;
 use std::error::Error;
 
 pub struct BlockchainDB {
     db: Db,
+    // Indice altezza -> hash, per leggere i blocchi in ordine senza tenerli in RAM
+    heights: Tree,
+    // ID transazione -> blocco che la contiene
+    receipts: Tree,
+    // Ultimo blocco firmato da noi e non ancora aggiunto alla catena
+    signed: Tree,
 }
 
 impl BlockchainDB {
     pub fn new(path: &str) -> Result<Self, Box<dyn Error>> {
-        let db = sled::open(path)?;
-        Ok(BlockchainDB { db })
+        Self::from_db(sled::open(path)?)
     }
 
-    pub fn save_block(&self, block: &Block) -> Result<(), Box<dyn Error>> {
-        let serialized = bincode::serialize(block)?;
-        self.db.insert(&block.hash, serialized)?;
-        self.db.flush()?;
-        Ok(())
+    // Database in memoria, cancellato alla chiusura (test)
+    #[cfg(test)]
+    pub fn temporary() -> Result<Self, Box<dyn Error>> {
+        Self::from_db(sled::Config::new().temporary(true).open()?)
+    }
+
+    fn from_db(db: Db) -> Result<Self, Box<dyn Error>> {
+        let heights = db.open_tree("heights")?;
+        let receipts = db.open_tree("receipts")?;
+        let signed = db.open_tree("signed_block")?;
+        Ok(BlockchainDB { db, heights, receipts, signed })
+    }
+
+    // Apre un albero separato nello stesso database (es. saldi dei conti)
+    pub fn open_tree(&self, name: &str) -> Result<Tree, Box<dyn Error>> {
+        Ok(self.db.open_tree(name)?)
+    }
+
+    // Scritture di un blocco (blocco, indice delle altezze, ricevute) con l'albero su cui vanno:
+    // le applica AccountState::apply_block nella stessa transazione dello stato
+    pub fn block_writes(&self, block: &Block) -> Result<[(&Tree, Batch); 3], Box<dyn Error>> {
+        let mut blocks = Batch::default();
+        blocks.insert(block.hash.as_bytes(), bincode::serialize(block)?);
+        let mut heights = Batch::default();
+        heights.insert(&block.index.to_be_bytes(), block.hash.as_bytes());
+        let mut receipts = Batch::default();
+        for (position, tx) in block.transactions.iter().enumerate() {
+            let receipt = Receipt { block_index: block.index, block_hash: block.hash.clone(), position: position as u32 };
+            receipts.insert(tx.id().as_bytes(), bincode::serialize(&receipt)?);
+        }
+        Ok([(&self.db, blocks), (&self.heights, heights), (&self.receipts, receipts)])
     }
 
     pub fn load_block(&self, hash: &str) -> Result<Option<Block>, Box<dyn Error>> {
@@ -28,4 +60,46 @@ impl BlockchainDB {
             None => Ok(None),
         }
     }
-}
\ No newline at end of file
+
+    pub fn load_block_at(&self, index: u64) -> Result<Option<Block>, Box<dyn Error>> {
+        match self.heights.get(index.to_be_bytes())? {
+            Some(hash) => self.load_block(&String::from_utf8(hash.to_vec())?),
+            None => Ok(None),
+        }
+    }
+
+    pub fn load_receipt(&self, tx_id: &str) -> Result<Option<Receipt>, Box<dyn Error>> {
+        match self.receipts.get(tx_id)? {
+            Some(data) => Ok(Some(bincode::deserialize(&data)?)),
+            None => Ok(None),
+        }
+    }
+
+    // Ultimo blocco salvato (le chiavi big-endian sono ordinate per altezza)
+    pub fn load_tip(&self) -> Result<Option<Block>, Box<dyn Error>> {
+        match self.heights.last()? {
+            Some((_, hash)) => self.load_block(&String::from_utf8(hash.to_vec())?),
+            None => Ok(None),
+        }
+    }
+
+    // Il firmatario non firma un secondo blocco alla stessa altezza: se l'aggiunta fallisce
+    // (anche dopo un riavvio) si ripropone questo blocco invece di prepararne uno nuovo
+    pub fn save_signed_block(&self, block: &Block) -> Result<(), Box<dyn Error>> {
+        self.signed.insert("block", bincode::serialize(block)?)?;
+        self.signed.flush()?;
+        Ok(())
+    }
+
+    pub fn load_signed_block(&self) -> Result<Option<Block>, Box<dyn Error>> {
+        match self.signed.get("block")? {
+            Some(data) => Ok(Some(bincode::deserialize(&data)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn clear_signed_block(&self) -> Result<(), Box<dyn Error>> {
+        self.signed.remove("block")?;
+        Ok(())
+    }
+}
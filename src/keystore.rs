@@ -0,0 +1,136 @@
+// File keystore cifrati: la chiave segreta Dilithium non tocca mai il disco in chiaro.
+// Argon2id deriva la chiave dalla passphrase, XChaCha20-Poly1305 cifra e autentica.
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs;
+use zeroize::Zeroizing;
+
+pub const KEYSTORE_VERSION: u32 = 1;
+
+// Parametri Argon2id di default: 64 MiB, 3 passaggi
+const DEFAULT_M_COST: u32 = 64 * 1024;
+const DEFAULT_T_COST: u32 = 3;
+const DEFAULT_P_COST: u32 = 1;
+const SALT_BYTES: usize = 16;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct KdfParams {
+    pub m_cost: u32,
+    pub t_cost: u32,
+    pub p_cost: u32,
+    pub salt: String, // hex
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Keystore {
+    pub version: u32,
+    pub address: String,
+    pub public_key: String, // hex, in chiaro: serve per mostrare l'indirizzo senza passphrase
+    pub kdf: String,
+    pub kdf_params: KdfParams,
+    pub cipher: String,
+    pub nonce: String,      // hex
+    pub ciphertext: String, // hex (chiave segreta cifrata + tag)
+}
+
+fn derive_key(passphrase: &str, params: &KdfParams) -> Result<Zeroizing<[u8; 32]>, Box<dyn Error>> {
+    let salt = hex::decode(&params.salt)?;
+    let argon_params = Params::new(params.m_cost, params.t_cost, params.p_cost, Some(32))
+        .map_err(|e| format!("invalid kdf params: {}", e))?;
+    let mut key = Zeroizing::new([0u8; 32]);
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, argon_params)
+        .hash_password_into(passphrase.as_bytes(), &salt, key.as_mut())
+        .map_err(|e| format!("key derivation failed: {}", e))?;
+    Ok(key)
+}
+
+impl Keystore {
+    pub fn encrypt(address: &str, public_key: &str, secret_key: &[u8], passphrase: &str) -> Result<Self, Box<dyn Error>> {
+        let mut salt = [0u8; SALT_BYTES];
+        OsRng.fill_bytes(&mut salt);
+        let kdf_params = KdfParams {
+            m_cost: DEFAULT_M_COST,
+            t_cost: DEFAULT_T_COST,
+            p_cost: DEFAULT_P_COST,
+            salt: hex::encode(salt),
+        };
+        let key = derive_key(passphrase, &kdf_params)?;
+        let cipher = XChaCha20Poly1305::new(key.as_ref().into());
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        // L'indirizzo è "associated data": un file con indirizzo manomesso non si decifra
+        let ciphertext = cipher
+            .encrypt(&nonce, Payload { msg: secret_key, aad: address.as_bytes() })
+            .map_err(|_| "encryption failed")?;
+
+        Ok(Keystore {
+            version: KEYSTORE_VERSION,
+            address: address.to_string(),
+            public_key: public_key.to_string(),
+            kdf: "argon2id".to_string(),
+            kdf_params,
+            cipher: "xchacha20poly1305".to_string(),
+            nonce: hex::encode(nonce),
+            ciphertext: hex::encode(ciphertext),
+        })
+    }
+
+    // Restituisce la chiave segreta in chiaro (azzerata automaticamente quando esce di scope)
+    pub fn decrypt(&self, passphrase: &str) -> Result<Zeroizing<Vec<u8>>, Box<dyn Error>> {
+        if self.version != KEYSTORE_VERSION || self.kdf != "argon2id" || self.cipher != "xchacha20poly1305" {
+            return Err(format!("unsupported keystore (version {}, {}, {})", self.version, self.kdf, self.cipher).into());
+        }
+        let nonce_bytes = hex::decode(&self.nonce)?;
+        if nonce_bytes.len() != 24 {
+            return Err("invalid keystore nonce".into());
+        }
+        let key = derive_key(passphrase, &self.kdf_params)?;
+        let cipher = XChaCha20Poly1305::new(key.as_ref().into());
+        let ciphertext = hex::decode(&self.ciphertext)?;
+        let secret_key = cipher
+            .decrypt(XNonce::from_slice(&nonce_bytes), Payload { msg: &ciphertext, aad: self.address.as_bytes() })
+            .map_err(|_| "wrong passphrase or corrupted keystore")?;
+        Ok(Zeroizing::new(secret_key))
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), Box<dyn Error>> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn load(path: &str) -> Result<Self, Box<dyn Error>> {
+        let content = fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADDRESS: &str = "adm0000000000000000000000000000000000000000000000000";
+
+    #[test]
+    fn round_trip_and_wrong_passphrase() {
+        let secret = b"dilithium secret key bytes".to_vec();
+        let keystore = Keystore::encrypt(ADDRESS, "05abcd", &secret, "correct horse").unwrap();
+        assert!(!keystore.ciphertext.contains(&hex::encode(&secret)));
+        assert_eq!(keystore.decrypt("correct horse").unwrap().as_slice(), secret.as_slice());
+
+        let error = keystore.decrypt("wrong horse").unwrap_err();
+        assert_eq!(error.to_string(), "wrong passphrase or corrupted keystore");
+
+        // L'indirizzo è autenticato: un file con indirizzo cambiato non si apre
+        let mut tampered = keystore.clone();
+        tampered.address = ADDRESS.replace('0', "1");
+        assert!(tampered.decrypt("correct horse").is_err());
+
+        // Versione sconosciuta: errore chiaro, nessun tentativo di decifrare
+        let mut future = keystore;
+        future.version = KEYSTORE_VERSION + 1;
+        assert!(future.decrypt("correct horse").unwrap_err().to_string().starts_with("unsupported keystore"));
+    }
+}
@@ -0,0 +1,23 @@
+// Moduli condivisi tra il nodo (main.rs), il wallet da riga di comando (wallet_cli.rs) e il firmatario (signer_daemon.rs)
+pub mod address;
+pub mod block;
+pub mod blockchain;
+pub mod certification;
+pub mod config;
+pub mod database;
+pub mod keystore;
+pub mod mempool;
+pub mod merkle;
+pub mod mnemonic;
+pub mod multisig;
+pub mod network_messages;
+pub mod notary;
+pub mod offline;
+pub mod receipts;
+pub mod signature;
+pub mod signer;
+pub mod state;
+pub mod supply;
+pub mod transaction;
+pub mod verifier;
+pub mod wallet;
@@ -6,17 +6,27 @@ use libp2p::{
 };
 use libp2p::futures::StreamExt;
 use libp2p::futures::future::Either; 
-use serde::{Deserialize, Serialize};
-use sha3::Sha3_512;
-use std::collections::hash_map::DefaultHasher;
+use sha3::{Digest, Sha3_256};
 use std::error::Error;
-use std::hash::{Hash, Hasher};
 use std::sync::{Arc, Mutex};
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::time::Duration;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use tokio::net::TcpListener;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Semaphore;
 use std::env;
 
+use adamas_core::{address, notary, offline, supply, verifier};
+use adamas_core::block::Block;
+use adamas_core::blockchain::Blockchain;
+use adamas_core::certification::CertificationRecord;
+use adamas_core::config::NodeConfig;
+use adamas_core::database::BlockchainDB;
+use adamas_core::mempool::Mempool;
+use adamas_core::network_messages::NetworkMessage;
+use adamas_core::receipts::TxStatus;
+use adamas_core::signer::{RemoteSigner, Signer};
+use adamas_core::transaction::{Transaction, TransactionKind};
+
 // =============================================================
 // ⚙️ CONFIGURAZIONE CLIENTE (MODIFICARE QUI PER NUOVI CLIENTI)
 // =============================================================
@@ -31,77 +41,22 @@ const DEFAULT_HTTP_PORT: &str = "3000";
 // 3. Nome della Rete (usato per isolare le comunicazioni)
 const NETWORK_TOPIC: &str = "adamas-enterprise-net";
 
-// =============================================================
+// 4. File di configurazione del nodo (se non specificato)
+const DEFAULT_CONFIG_FILE: &str = "node_config.json";
 
-// --- STRUTTURE BLOCKCHAIN ---
-#[derive(Serialize, Deserialize, Debug, Clone)]
-struct Block {
-    index: u32,
-    timestamp: u128,
-    data: String,
-    previous_hash: String,
-    hash: String,
-    node_id: String,
-}
+// 5. Dimensione massima di una richiesta HTTP (una transazione Dilithium pesa ~15 KB)
+const MAX_REQUEST_BYTES: usize = 256 * 1024;
 
-struct Blockchain {
-    chain: Vec<Block>,
-}
+// 6. Dimensione massima di un documento caricato su /documents/verify (PDF, certificati di laboratorio),
+// quanti caricamenti si servono insieme e in quanto tempo deve arrivare ciascuno
+const MAX_DOCUMENT_BYTES: usize = 16 * 1024 * 1024;
+const MAX_CONCURRENT_UPLOADS: usize = 4;
+const UPLOAD_TIMEOUT: Duration = Duration::from_secs(120);
 
-impl Blockchain {
-    fn new() -> Self {
-        let mut chain = Blockchain { chain: Vec::new() };
-        chain.create_genesis_block();
-        chain
-    }
+// 7. Risposta di /mine/ e /certifications su un nodo senza firmatario: i peer accettano solo blocchi firmati
+const NO_SIGNER: &str = "this node has no block signer: start adamas-signer and set signer_socket in node_config.json";
 
-    fn create_genesis_block(&mut self) {
-        let genesis_block = Block {
-            index: 0,
-            timestamp: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis(),
-            data: "GENESIS BLOCK".to_string(),
-            previous_hash: "0".to_string(),
-            hash: "00000000000000000000".to_string(),
-            node_id: "SYSTEM".to_string(),
-        };
-        self.chain.push(genesis_block);
-    }
-
-    fn add_block(&mut self, data: String, node_origin: String) -> Block {
-        let previous_block = self.chain.last().unwrap();
-        let new_index = previous_block.index + 1;
-        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis();
-        let previous_hash = previous_block.hash.clone();
-        
-        use sha3::Digest; 
-        let content_to_hash = format!("{}{}{}{}", new_index, timestamp, data, previous_hash);
-        let mut hasher = Sha3_512::new();
-        hasher.update(content_to_hash);
-        let result = hasher.finalize();
-        let hash = format!("{:x}", result);
-
-        let new_block = Block {
-            index: new_index,
-            timestamp,
-            data,
-            previous_hash,
-            hash,
-            node_id: node_origin,
-        };
-
-        self.chain.push(new_block.clone());
-        println!("✅ BLOCK #{} MINED: {}", new_index, new_block.data);
-        new_block
-    }
-
-    fn receive_block(&mut self, remote_block: Block) {
-        let last_index = self.chain.last().unwrap().index;
-        if remote_block.index > last_index {
-            self.chain.push(remote_block.clone());
-            println!("📥 SYNC: Block #{} received from {}", remote_block.index, remote_block.node_id);
-        }
-    }
-}
+// =============================================================
 
 // --- NETWORK BEHAVIOUR ---
 #[derive(NetworkBehaviour)]
@@ -116,6 +71,10 @@ async fn main() -> Result<(), Box<dyn Error>> {
     let args: Vec<String> = env::args().collect();
     let http_port = args.get(1).unwrap_or(&DEFAULT_HTTP_PORT.to_string()).clone();
     let relay_addr_str = args.get(2).map(|s| s.as_str()).unwrap_or(BOOTSTRAP_RELAY);
+    let config_file = args.get(3).map(|s| s.as_str()).unwrap_or(DEFAULT_CONFIG_FILE);
+
+    let config = NodeConfig::load(config_file)?;
+    println!("⚙️ CONFIG: {} ({}) -> DB: {}", config.chain_name, config.node_role, config.db_path);
 
     let local_key = libp2p::identity::Keypair::generate_ed25519();
     let local_peer_id = libp2p::PeerId::from(local_key.public());
@@ -147,14 +106,17 @@ async fn main() -> Result<(), Box<dyn Error>> {
                 .boxed()
         })?
         .with_behaviour(|key: &libp2p::identity::Keypair| {
+            // ID = hash di tutti i byte del messaggio: un campo dichiarato dal mittente (hash del blocco, ID della
+            // transazione senza firme) permetterebbe a una copia falsa di far scartare quella vera come duplicato.
+            // Le transazioni già note si riconoscono per ID in admit_relayed.
             let message_id_fn = |message: &gossipsub::Message| {
-                let mut s = DefaultHasher::new();
-                message.data.hash(&mut s);
-                gossipsub::MessageId::from(s.finish().to_string())
+                gossipsub::MessageId::from(hex::encode(Sha3_256::digest(&message.data)))
             };
+            // validate_messages: un messaggio si inoltra ai peer solo dopo che il nodo l'ha validato
             let gossipsub_config = gossipsub::ConfigBuilder::default()
                 .heartbeat_interval(Duration::from_secs(10))
                 .validation_mode(gossipsub::ValidationMode::Strict)
+                .validate_messages()
                 .message_id_fn(message_id_fn)
                 .build()
                 .map_err(|msg| std::io::Error::new(std::io::ErrorKind::Other, msg))?;
@@ -194,11 +156,31 @@ async fn main() -> Result<(), Box<dyn Error>> {
     println!("🚀 ADAMAS CLIENT v3.4 STARTED");
     println!("🌍 Dashboard: http://localhost:{}", http_port);
 
-    let blockchain = Arc::new(Mutex::new(Blockchain::new()));
+    let blockchain = Arc::new(Mutex::new(Blockchain::new(BlockchainDB::new(&config.db_path)?)?));
     let blockchain_web = blockchain.clone();
-    let (tx_p2p, mut rx_p2p) = tokio::sync::mpsc::unbounded_channel::<String>();
+    let mempool = Arc::new(Mutex::new(Mempool::new(config.min_relay_fee, config.mempool.clone())));
+    println!("📥 MEMPOOL: max {} tx, {} byte, {} per mittente", config.mempool.max_transactions, config.mempool.max_bytes, config.mempool.max_per_sender);
+    // Chiave del validatore fuori dal processo: il nodo chiede le firme dei blocchi ad adamas-signer.
+    // Senza firmatario il nodo segue la catena ma non produce blocchi (i peer accettano solo blocchi firmati).
+    let block_signer = if config.signer_socket.is_empty() {
+        println!("⚠️ NO BLOCK SIGNER: questo nodo non produce blocchi (signer_socket vuoto in {})", config_file);
+        None
+    } else {
+        let signer = RemoteSigner::connect(&config.signer_socket)?;
+        // Commissioni e ricompense vanno sempre all'indirizzo della chiave che firma il blocco
+        if !config.reward_address.is_empty() && config.reward_address != signer.address() {
+            return Err(format!("reward_address {} is not the signer address {}: blocks pay the key that signs them", config.reward_address, signer.address()).into());
+        }
+        println!("🔏 BLOCK SIGNER: {} ({})", signer.address(), config.signer_socket);
+        Some(signer)
+    };
+    let mempool_web = mempool.clone();
+    let (tx_p2p, mut rx_p2p) = tokio::sync::mpsc::unbounded_channel::<NetworkMessage>();
+    // Esiti delle validazioni fatte fuori dal ciclo P2P (firme verificate sui thread bloccanti)
+    let (validated_tx, mut validated_rx) = tokio::sync::mpsc::unbounded_channel::<(gossipsub::MessageId, libp2p::PeerId, gossipsub::MessageAcceptance)>();
 
     // WEB SERVER
+    let upload_slots = Arc::new(Semaphore::new(MAX_CONCURRENT_UPLOADS));
     tokio::spawn(async move {
         let addr = format!("0.0.0.0:{}", http_port);
         let listener = TcpListener::bind(&addr).await.unwrap();
@@ -206,31 +188,275 @@ async fn main() -> Result<(), Box<dyn Error>> {
         loop {
             if let Ok((mut socket, _)) = listener.accept().await {
                 let blockchain_ref = blockchain_web.clone();
+                let mempool_ref = mempool_web.clone();
+                let block_signer = block_signer.clone();
                 let tx_p2p_ref = tx_p2p.clone();
+                let upload_slots = upload_slots.clone();
                 tokio::spawn(async move {
-                    let mut buffer = [0; 1024];
-                    if socket.read(&mut buffer).await.is_ok() {
-                        let request = String::from_utf8_lossy(&buffer[..]);
+                    if let Some(Request { raw, document }) = read_request(&mut socket, &upload_slots).await {
+                        let request = String::from_utf8_lossy(&raw).to_string();
+                        let body = request.split_once("\r\n\r\n").map(|(_, body)| body).unwrap_or("");
                         let response_body;
                         let response_header;
 
                         if request.starts_with("GET /blocks") {
+                            // Solo gli ultimi blocchi in cache: la risposta non cresce con la catena
                             let chain = blockchain_ref.lock().unwrap();
-                            response_body = serde_json::to_string(&chain.chain).unwrap();
+                            response_body = serde_json::to_string(chain.recent_blocks()).unwrap();
                             response_header = "HTTP/1.1 200 OK\r\nContent-Type: application/json; charset=UTF-8";
+                        } else if request.starts_with("GET /block/") {
+                            // Blocchi più vecchi: letti dal database su richiesta
+                            let parts: Vec<&str> = request.split_whitespace().collect();
+                            let found = parts.get(1)
+                                .and_then(|path| path["/block/".len()..].parse::<u64>().ok())
+                                .and_then(|index| blockchain_ref.lock().unwrap().get_block(index).ok().flatten());
+                            match found {
+                                Some(block) => {
+                                    response_body = serde_json::to_string(&block).unwrap();
+                                    response_header = "HTTP/1.1 200 OK\r\nContent-Type: application/json; charset=UTF-8";
+                                }
+                                None => {
+                                    response_body = "{}".to_string();
+                                    response_header = "HTTP/1.1 404 NOT FOUND";
+                                }
+                            }
+                        } else if request.starts_with("GET /accounts/") {
+                            let parts: Vec<&str> = request.split_whitespace().collect();
+                            let address = parts.get(1).map(|path| &path["/accounts/".len()..]).unwrap_or("");
+                            if !address::is_valid(address) {
+                                response_body = serde_json::json!({ "error": format!("invalid address: {}", address) }).to_string();
+                                response_header = "HTTP/1.1 400 ERROR\r\nContent-Type: application/json";
+                            } else {
+                                let account = {
+                                    let chain = blockchain_ref.lock().unwrap();
+                                    let state = chain.state();
+                                    state.balance(address).and_then(|balance| {
+                                        let nonce = state.nonce(address)?;
+                                        let public_key = state.public_key(address)?;
+                                        let history = state.history(address)?;
+                                        Ok((balance, nonce, public_key, history))
+                                    }).ok()
+                                };
+                                match account {
+                                    Some((balance, nonce, public_key, history)) => {
+                                        response_body = serde_json::json!({
+                                            "address": address,
+                                            "valid": address::is_valid(address),
+                                            "balance": balance,
+                                            "nonce": nonce,
+                                            "public_key": public_key,
+                                            "history": history,
+                                        }).to_string();
+                                        response_header = "HTTP/1.1 200 OK\r\nContent-Type: application/json; charset=UTF-8";
+                                    }
+                                    None => {
+                                        response_body = "{}".to_string();
+                                        response_header = "HTTP/1.1 500 ERROR";
+                                    }
+                                }
+                            }
+                        } else if request.starts_with("GET /proof/") {
+                            // Prova di stato per un conto, verificabile contro lo state_root dell'ultimo blocco
+                            let parts: Vec<&str> = request.split_whitespace().collect();
+                            let address = parts.get(1).map(|path| &path["/proof/".len()..]).unwrap_or("");
+                            if !address::is_valid(address) {
+                                response_body = serde_json::json!({ "error": format!("invalid address: {}", address) }).to_string();
+                                response_header = "HTTP/1.1 400 ERROR\r\nContent-Type: application/json";
+                            } else {
+                                let proof = {
+                                    let chain = blockchain_ref.lock().unwrap();
+                                    chain.state().prove_account(address).ok().map(|proof| (chain.height(), proof))
+                                };
+                                match proof {
+                                    Some((height, proof)) => {
+                                        response_body = serde_json::json!({
+                                            "height": height,
+                                            "proof": proof,
+                                        }).to_string();
+                                        response_header = "HTTP/1.1 200 OK\r\nContent-Type: application/json; charset=UTF-8";
+                                    }
+                                    None => {
+                                        response_body = "{}".to_string();
+                                        response_header = "HTTP/1.1 500 ERROR";
+                                    }
+                                }
+                            }
+                        } else if request.starts_with("GET /documents/") || request.starts_with("POST /documents/verify") {
+                            // Notarizzazione: impronta nell'URL oppure file caricato (SHA3-256 calcolato in read_request)
+                            let hash = if request.starts_with("POST") {
+                                document.unwrap_or_else(|| Ok(notary::hash_file(&[])))
+                            } else {
+                                let parts: Vec<&str> = request.split_whitespace().collect();
+                                Ok(parts.get(1).map(|path| path["/documents/".len()..].to_lowercase()).unwrap_or_default())
+                            };
+                            match hash {
+                                Ok(hash) => match notary::DocumentRecord::new(hash.clone()).validate() {
+                                    Ok(()) => match document_report(&blockchain_ref, &hash) {
+                                        Some(report) => {
+                                            response_body = report.to_string();
+                                            response_header = "HTTP/1.1 200 OK\r\nContent-Type: application/json";
+                                        }
+                                        None => {
+                                            response_body = "{}".to_string();
+                                            response_header = "HTTP/1.1 500 ERROR";
+                                        }
+                                    },
+                                    Err(e) => {
+                                        response_body = serde_json::json!({ "error": e.to_string() }).to_string();
+                                        response_header = "HTTP/1.1 400 ERROR\r\nContent-Type: application/json";
+                                    }
+                                },
+                                // Troppi caricamenti in corso
+                                Err(busy) => {
+                                    response_body = serde_json::json!({ "error": busy }).to_string();
+                                    response_header = "HTTP/1.1 503 SERVICE UNAVAILABLE\r\nContent-Type: application/json\r\nRetry-After: 5";
+                                }
+                            }
+                        } else if request.starts_with("GET /supply") {
+                            let report = {
+                                let chain = blockchain_ref.lock().unwrap();
+                                let height = chain.height();
+                                chain.state().circulating_supply().map(|circulating| (height, circulating)).ok()
+                            };
+                            match report {
+                                Some((height, circulating)) => {
+                                    response_body = serde_json::json!({
+                                        "height": height,
+                                        "decimals": supply::DECIMALS,
+                                        "max_supply": supply::MAX_SUPPLY,
+                                        "circulating": circulating,
+                                        "remaining": supply::MINTABLE_SUPPLY.saturating_sub(circulating),
+                                        "next_block_reward": supply::block_reward(height + 1, circulating),
+                                    }).to_string();
+                                    response_header = "HTTP/1.1 200 OK\r\nContent-Type: application/json; charset=UTF-8";
+                                }
+                                None => {
+                                    response_body = "{}".to_string();
+                                    response_header = "HTTP/1.1 500 ERROR";
+                                }
+                            }
+                        } else if request.starts_with("POST /transactions") {
+                            // JSON oppure testo "ADMSTX2:..." prodotto dalla firma offline
+                            // Errori come String: devono attraversare gli await
+                            let result = match offline::parse_signed(body).map_err(|e| e.to_string()) {
+                                Ok(tx) => {
+                                    let id = tx.id();
+                                    let (expected_nonce, registered_key, balance, next_height) = {
+                                        let chain = blockchain_ref.lock().unwrap();
+                                        let state = chain.state();
+                                        (state.nonce(&tx.sender).unwrap_or(0), state.public_key(&tx.sender).ok().flatten(), state.balance(&tx.sender).unwrap_or(0), chain.height() + 1)
+                                    };
+                                    // Firma verificata sul pool di thread bloccanti: il runtime async resta libero
+                                    // e la mempool trova il risultato in cache
+                                    let key = registered_key.clone();
+                                    let verified = tokio::task::spawn_blocking(move || {
+                                        let valid = verifier::verify(&tx, key.as_deref());
+                                        (tx, valid)
+                                    }).await;
+                                    let admitted = match verified {
+                                        Ok((tx, true)) => {
+                                            let relay = tx.clone();
+                                            mempool_ref.lock().unwrap().add_transaction(tx, expected_nonce, registered_key.as_deref(), balance, next_height).map(|()| relay)
+                                        }
+                                        // Non si registra sotto l'ID: la copia con la firma sbagliata non è la transazione del mittente
+                                        _ => {
+                                            println!("   [MEMPOOL] ❌ Rifiutata transazione invalida (Firma errata)");
+                                            Err("invalid signature".to_string())
+                                        }
+                                    };
+                                    match admitted {
+                                        Ok(relay) => {
+                                            // Accettata qui: la propaghiamo alle mempool degli altri nodi
+                                            let _ = tx_p2p_ref.send(NetworkMessage::Transaction(relay));
+                                            Ok(id)
+                                        }
+                                        Err(reason) => Err((Some(id), reason)),
+                                    }
+                                }
+                                Err(e) => Err((None, format!("malformed transaction: {}", e))),
+                            };
+                            match result {
+                                Ok(id) => {
+                                    response_body = serde_json::json!({ "status": "ok", "id": id }).to_string();
+                                    response_header = "HTTP/1.1 200 OK\r\nContent-Type: application/json";
+                                }
+                                Err((id, reason)) => {
+                                    response_body = serde_json::json!({ "status": "rejected", "id": id, "reason": reason }).to_string();
+                                    response_header = "HTTP/1.1 400 ERROR\r\nContent-Type: application/json";
+                                }
+                            }
+                        } else if request.starts_with("GET /tx/") {
+                            // Ricevuta: in attesa, incluso all'altezza H, definitivo o rifiutato (con il motivo)
+                            let parts: Vec<&str> = request.split_whitespace().collect();
+                            let id = parts.get(1).map(|path| &path["/tx/".len()..]).unwrap_or("");
+                            let on_chain = blockchain_ref.lock().unwrap().tx_status(id).ok().flatten();
+                            let status = on_chain
+                                .or_else(|| mempool_ref.lock().unwrap().status(id))
+                                .unwrap_or(TxStatus::Unknown);
+                            let mut receipt = serde_json::to_value(&status).unwrap();
+                            receipt["id"] = id.into();
+                            response_body = receipt.to_string();
+                            response_header = if status == TxStatus::Unknown {
+                                "HTTP/1.1 404 NOT FOUND\r\nContent-Type: application/json"
+                            } else {
+                                "HTTP/1.1 200 OK\r\nContent-Type: application/json"
+                            };
+                        } else if request.starts_with("POST /certifications") {
+                            // Record strutturato dalla dashboard: lo firma la chiave del nodo (adamas-signer)
+                            // e finisce subito in un blocco, come prima faceva /mine/ con il testo
+                            let result = match (serde_json::from_str::<CertificationRecord>(body), block_signer.clone()) {
+                                (Ok(record), Some(signer)) => {
+                                    let blockchain = blockchain_ref.clone();
+                                    let mempool = mempool_ref.clone();
+                                    tokio::task::spawn_blocking(move || certify(record, &signer, &blockchain, &mempool))
+                                        .await
+                                        .unwrap_or_else(|e| Err(e.to_string()))
+                                }
+                                (Err(e), _) => Err(format!("invalid record: {}", e)),
+                                (Ok(_), None) => Err(format!("{}: sign the record with adamas-wallet certify", NO_SIGNER)),
+                            };
+                            match result {
+                                Ok(block) => {
+                                    let _ = tx_p2p_ref.send(NetworkMessage::Block(block.clone()));
+                                    response_body = serde_json::json!({
+                                        "status": "ok",
+                                        "block": block.index,
+                                        "hash": block.hash,
+                                    }).to_string();
+                                    response_header = "HTTP/1.1 200 OK\r\nContent-Type: application/json";
+                                }
+                                Err(e) => {
+                                    println!("   [CERTIFY] ❌ {}", e);
+                                    response_body = serde_json::json!({ "status": "rejected", "error": e }).to_string();
+                                    response_header = "HTTP/1.1 400 ERROR\r\nContent-Type: application/json";
+                                }
+                            }
                         } else if request.contains("GET /mine/") {
                             let parts: Vec<&str> = request.split_whitespace().collect();
                             if parts.len() > 1 && parts[1].len() > 6 {
-                                let data_raw = &parts[1][6..];
-                                let data_clean = data_raw.replace("%20", " ").replace("%7C", " | ").replace("%22", "");
-                                let new_block = {
-                                    let mut chain = blockchain_ref.lock().unwrap();
-                                    chain.add_block(data_clean, "WEB_USER".to_string())
-                                };
-                                let block_json = serde_json::to_string(&new_block).unwrap();
-                                let _ = tx_p2p_ref.send(block_json);
-                                response_body = "{\"status\": \"ok\"}".to_string();
-                                response_header = "HTTP/1.1 200 OK\r\nContent-Type: application/json";
+                                let data_clean = percent_decode(&parts[1][6..]);
+                                match &block_signer {
+                                    Some(signer) => {
+                                        let signer = signer.clone();
+                                        let blockchain = blockchain_ref.clone();
+                                        let mempool = mempool_ref.clone();
+                                        let mined = tokio::task::spawn_blocking(move || mine_block(&blockchain, &mempool, data_clean, &signer))
+                                            .await
+                                            .unwrap_or(None);
+                                        if let Some(new_block) = mined {
+                                            let _ = tx_p2p_ref.send(NetworkMessage::Block(new_block));
+                                            response_body = "{\"status\": \"ok\"}".to_string();
+                                            response_header = "HTTP/1.1 200 OK\r\nContent-Type: application/json";
+                                        } else {
+                                            response_body = "{}".to_string();
+                                            response_header = "HTTP/1.1 500 ERROR";
+                                        }
+                                    }
+                                    None => {
+                                        response_body = serde_json::json!({ "status": "rejected", "error": NO_SIGNER }).to_string();
+                                        response_header = "HTTP/1.1 400 ERROR\r\nContent-Type: application/json";
+                                    }
+                                }
                             } else {
                                 response_body = "{}".to_string();
                                 response_header = "HTTP/1.1 400 ERROR";
@@ -266,21 +492,278 @@ async fn main() -> Result<(), Box<dyn Error>> {
                         swarm.behaviour_mut().gossipsub.add_explicit_peer(&peer_id);
                     }
                 },
-                SwarmEvent::Behaviour(AdamasBehaviourEvent::Gossipsub(gossipsub::Event::Message { message, .. })) => {
-                    let msg_str = String::from_utf8_lossy(&message.data);
-                    if let Ok(remote_block) = serde_json::from_str::<Block>(&msg_str) {
-                        let mut chain = blockchain.lock().unwrap();
-                        chain.receive_block(remote_block);
+                SwarmEvent::Behaviour(AdamasBehaviourEvent::Gossipsub(gossipsub::Event::Message { propagation_source, message_id, message })) => {
+                    let acceptance = match NetworkMessage::decode(&message.data) {
+                        Some(NetworkMessage::Block(remote_block)) => {
+                            // Verifica delle firme e applicazione del blocco fuori dal runtime: l'esito arriva da `validated_rx`
+                            let (blockchain, mempool, validated_tx) = (blockchain.clone(), mempool.clone(), validated_tx.clone());
+                            let message_id = message_id.clone();
+                            tokio::task::spawn_blocking(move || {
+                                let acceptance = admit_block(remote_block, &blockchain, &mempool);
+                                let _ = validated_tx.send((message_id, propagation_source, acceptance));
+                            });
+                            None
+                        }
+                        Some(NetworkMessage::Transaction(tx)) => {
+                            // Una raffica di firme SPHINCS+ non deve fermare il ciclo P2P: l'esito arriva da `validated_rx`
+                            let (blockchain, mempool, validated_tx) = (blockchain.clone(), mempool.clone(), validated_tx.clone());
+                            let message_id = message_id.clone();
+                            tokio::task::spawn_blocking(move || {
+                                let acceptance = admit_relayed(tx, &blockchain, &mempool);
+                                let _ = validated_tx.send((message_id, propagation_source, acceptance));
+                            });
+                            None
+                        }
+                        None => Some(gossipsub::MessageAcceptance::Reject),
+                    };
+                    if let Some(acceptance) = acceptance {
+                        let _ = swarm.behaviour_mut().gossipsub.report_message_validation_result(&message_id, &propagation_source, acceptance);
                     }
                 },
                 _ => {}
             },
-            Some(block_json) = rx_p2p.recv() => {
+            Some((message_id, propagation_source, acceptance)) = validated_rx.recv() => {
+                let _ = swarm.behaviour_mut().gossipsub.report_message_validation_result(&message_id, &propagation_source, acceptance);
+            }
+            Some(outgoing) = rx_p2p.recv() => {
                 let topic = gossipsub::IdentTopic::new(NETWORK_TOPIC);
-                if let Err(e) = swarm.behaviour_mut().gossipsub.publish(topic, block_json.as_bytes()) {
-                    println!("❌ Broadcast Error: {:?}", e);
+                match outgoing.encode() {
+                    Ok(data) => {
+                        if let Err(e) = swarm.behaviour_mut().gossipsub.publish(topic, data) {
+                            println!("❌ Broadcast Error: {:?}", e);
+                        }
+                    }
+                    Err(e) => println!("❌ Broadcast Error: {}", e),
+                }
+            }
+        }
+    }
+}
+
+// Blocco ricevuto dal gossip (gira sul pool di thread bloccanti): si inoltra solo se è stato applicato.
+fn admit_block(block: Block, blockchain: &Mutex<Blockchain>, mempool: &Mutex<Mempool>) -> gossipsub::MessageAcceptance {
+    let mut chain = blockchain.lock().unwrap();
+    match chain.receive_block(block) {
+        Ok(true) => {
+            mempool.lock().unwrap().remove_stale(chain.height() + 1, |sender| chain.state().nonce(sender).unwrap_or(0), |tx| chain.state().invalidation(tx));
+            gossipsub::MessageAcceptance::Accept
+        }
+        // Blocco vecchio o già noto: non è stato validato, quindi non si inoltra
+        Ok(false) => gossipsub::MessageAcceptance::Ignore,
+        Err(e) => {
+            println!("❌ Block Rejected: {}", e);
+            gossipsub::MessageAcceptance::Reject
+        }
+    }
+}
+
+// Transazione ricevuta dal gossip (gira sul pool di thread bloccanti): si inoltra ai peer solo se entra nella nostra mempool.
+// Una firma non valida penalizza il peer (Reject); duplicati e transazioni che la mempool
+// non vuole (nonce già usato, commissione bassa, ...) semplicemente non si propagano (Ignore).
+fn admit_relayed(tx: Transaction, blockchain: &Mutex<Blockchain>, mempool: &Mutex<Mempool>) -> gossipsub::MessageAcceptance {
+    let id = tx.id();
+    let (expected_nonce, registered_key, balance, next_height, included) = {
+        let chain = blockchain.lock().unwrap();
+        let state = chain.state();
+        let included = chain.receipt(&id).ok().flatten().is_some();
+        (state.nonce(&tx.sender).unwrap_or(0), state.public_key(&tx.sender).ok().flatten(), state.balance(&tx.sender).unwrap_or(0), chain.height() + 1, included)
+    };
+    if included || mempool.lock().unwrap().status(&id) == Some(TxStatus::Pending) {
+        return gossipsub::MessageAcceptance::Ignore;
+    }
+    // La verifica della firma è lenta: senza tenere bloccata la mempool
+    if !verifier::verify(&tx, registered_key.as_deref()) {
+        println!("   [GOSSIP] ❌ Transazione {} con firma non valida", id);
+        return gossipsub::MessageAcceptance::Reject;
+    }
+    match mempool.lock().unwrap().add_transaction(tx, expected_nonce, registered_key.as_deref(), balance, next_height) {
+        Ok(()) => {
+            println!("   [GOSSIP] 📥 Transazione {} ricevuta da un peer", id);
+            gossipsub::MessageAcceptance::Accept
+        }
+        Err(_) => gossipsub::MessageAcceptance::Ignore,
+    }
+}
+
+// Produce un blocco con le transazioni pronte della mempool
+fn mine_block(blockchain: &Mutex<Blockchain>, mempool: &Mutex<Mempool>, data: String, signer: &dyn Signer) -> Option<Block> {
+    // La firma può richiedere un giro fino ad adamas-signer: catena e mempool non restano bloccate nel frattempo
+    let prepared = {
+        let chain = blockchain.lock().unwrap();
+        let pending = mempool.lock().unwrap().ready_transactions(chain.height() + 1, |sender| chain.state().nonce(sender).unwrap_or(0));
+        chain.prepare_block(data, pending, "WEB_USER".to_string(), signer.public_key())
+    };
+    let result = prepared.and_then(|mut block| {
+        block.signature = signer.sign_block(block.index, &block.hash)?;
+        let mut chain = blockchain.lock().unwrap();
+        chain.commit_block(block.clone())?;
+        mempool.lock().unwrap()
+            .remove_stale(chain.height() + 1, |sender| chain.state().nonce(sender).unwrap_or(0), |tx| chain.state().invalidation(tx));
+        Ok(block)
+    });
+    match result {
+        Ok(block) => Some(block),
+        Err(e) => {
+            println!("❌ Block Error: {}", e);
+            None
+        }
+    }
+}
+
+// Certificazione dalla dashboard: transazione CertifyRecord dal conto del firmatario del nodo, poi un blocco
+fn certify(record: CertificationRecord, signer: &RemoteSigner, blockchain: &Mutex<Blockchain>, mempool: &Mutex<Mempool>) -> Result<Block, String> {
+    record.validate().map_err(|e| e.to_string())?;
+    let summary = record.summary();
+    let sender = signer.address();
+    let (expected_nonce, registered_key, balance, next_height) = {
+        let chain = blockchain.lock().unwrap();
+        let state = chain.state();
+        (state.nonce(&sender).unwrap_or(0), state.public_key(&sender).ok().flatten(), state.balance(&sender).unwrap_or(0), chain.height() + 1)
+    };
+    let (nonce, min_relay_fee) = {
+        let pool = mempool.lock().unwrap();
+        (pool.next_nonce(&sender, expected_nonce), pool.min_relay_fee)
+    };
+
+    let mut tx = Transaction::unsigned(sender.clone(), sender, 0, 0, nonce);
+    tx.kind = TransactionKind::CertifyRecord(record);
+    tx.sign(signer).map_err(|e| e.to_string())?;
+    // La commissione non cambia la dimensione (u64 a lunghezza fissa): se serve si firma di nuovo con quella giusta
+    let fee = (min_relay_fee * tx.size()).div_ceil(1000);
+    if fee > 0 {
+        tx.fee = fee;
+        tx.sign(signer).map_err(|e| e.to_string())?;
+    }
+    mempool.lock().unwrap().add_transaction(tx, expected_nonce, registered_key.as_deref(), balance, next_height)
+        .map_err(|reason| format!("certification rejected by the mempool: {}", reason))?;
+    println!("   [CERTIFY] 📄 {}", summary);
+    mine_block(blockchain, mempool, summary, signer).ok_or_else(|| "block production failed".to_string())
+}
+
+// Ancoraggio di un documento (se c'è) con la prova di stato contro lo state_root dell'ultimo blocco.
+// Senza ancoraggio la prova ha valore vuoto: dimostra che l'impronta non è mai stata ancorata.
+fn document_report(blockchain: &Mutex<Blockchain>, hash: &str) -> Option<serde_json::Value> {
+    let chain = blockchain.lock().unwrap();
+    let anchor = chain.state().document(hash).ok()?;
+    let proof = chain.state().prove_document(hash).ok()?;
+    let tip = chain.last_block();
+    let mut report = serde_json::json!({
+        "hash": hash,
+        "anchored": anchor.is_some(),
+        "anchor": anchor,
+        "proof_height": tip.index,
+        "proof_block_hash": tip.hash,
+        "proof": proof,
+    });
+    if let Some(anchor) = anchor {
+        let block_hash = chain.receipt(&anchor.tx_id).ok().flatten().map(|receipt| receipt.block_hash).unwrap_or_default();
+        report["height"] = anchor.block_index.into();
+        report["block_hash"] = block_hash.into();
+        report["confirmations"] = (tip.index.saturating_sub(anchor.block_index) + 1).into();
+    }
+    Some(report)
+}
+
+// Decodifica %XX negli URL (es. il testo inviato a /mine/ dalla dashboard)
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| text.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+// Richiesta HTTP letta dal socket. Un file caricato su /documents/verify non resta in memoria:
+// `raw` ne contiene solo l'header e `document` l'impronta SHA3-256 (Err se troppi caricamenti sono in corso).
+struct Request {
+    raw: Vec<u8>,
+    document: Option<Result<String, String>>,
+}
+
+// Legge una richiesta HTTP completa: header e body (fino a Content-Length).
+async fn read_request(socket: &mut TcpStream, upload_slots: &Semaphore) -> Option<Request> {
+    let mut data = Vec::new();
+    let mut buffer = [0; 4096];
+    let mut expected_len = None;
+    loop {
+        let n = socket.read(&mut buffer).await.ok()?;
+        if n == 0 {
+            break;
+        }
+        data.extend_from_slice(&buffer[..n]);
+        if expected_len.is_none() {
+            if let Some(end) = data.windows(4).position(|w| w == b"\r\n\r\n") {
+                let head = String::from_utf8_lossy(&data[..end]).to_lowercase();
+                let content_length = head.lines()
+                    .find_map(|line| line.strip_prefix("content-length:"))
+                    .and_then(|value| value.trim().parse::<usize>().ok())
+                    .unwrap_or(0);
+                // Solo il caricamento di un documento può superare il limite normale, e non si bufferizza
+                if head.starts_with("post /documents/verify") {
+                    let received = data.split_off(end + 4);
+                    let document = hash_upload(socket, received, content_length, upload_slots).await?;
+                    return Some(Request { raw: data, document: Some(document) });
                 }
+                expected_len = Some(end + 4 + content_length);
             }
         }
+        if data.len() > MAX_REQUEST_BYTES {
+            return None;
+        }
+        if expected_len.is_some_and(|expected| data.len() >= expected) {
+            break;
+        }
+    }
+    if data.is_empty() {
+        return None;
+    }
+    Some(Request { raw: data, document: None })
+}
+
+// SHA3-256 del documento calcolato man mano che arriva (`received` è la parte già letta con l'header).
+// Al massimo MAX_CONCURRENT_UPLOADS caricamenti insieme, ciascuno entro UPLOAD_TIMEOUT.
+async fn hash_upload(socket: &mut TcpStream, received: Vec<u8>, content_length: usize, upload_slots: &Semaphore) -> Option<Result<String, String>> {
+    if content_length > MAX_DOCUMENT_BYTES {
+        return None;
     }
-}
\ No newline at end of file
+    let Ok(_slot) = upload_slots.try_acquire() else {
+        println!("   [NOTARY] ⏳ Troppi caricamenti in corso: documento rifiutato");
+        return Some(Err("too many document uploads in progress, retry later".to_string()));
+    };
+    let mut hasher = Sha3_256::new();
+    let mut remaining = content_length;
+    let first = &received[..received.len().min(remaining)];
+    hasher.update(first);
+    remaining -= first.len();
+
+    let mut buffer = [0; 16 * 1024];
+    let upload = async {
+        while remaining > 0 {
+            let chunk = remaining.min(buffer.len());
+            let n = socket.read(&mut buffer[..chunk]).await.ok()?;
+            if n == 0 {
+                return None;
+            }
+            hasher.update(&buffer[..n]);
+            remaining -= n;
+        }
+        Some(())
+    };
+    tokio::time::timeout(UPLOAD_TIMEOUT, upload).await.ok()??;
+    Some(Ok(hex::encode(hasher.finalize())))
+}
@@ -1,38 +1,588 @@
-use std::collections::HashMap;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap, HashMap, VecDeque};
+use crate::address;
+use crate::receipts::{RejectionLog, TxStatus};
 use crate::transaction::Transaction;
+use crate::verifier;
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Capienza della mempool e regole di sostituzione (sezione "mempool" di node_config.json)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct MempoolLimits {
+    pub max_transactions: usize,
+    pub max_bytes: u64,
+    pub max_per_sender: usize, // Transazioni in attesa per mittente, nonce futuri compresi
+    // Aumento minimo della commissione (in %) per sostituire una transazione in attesa con lo stesso nonce
+    pub replacement_bump_percent: u64,
+}
+
+// Transazione che si può espellere: (nonce, commissione ogni 1000 byte, peso in byte)
+type Candidate = (u64, u64, u64);
+
+impl Default for MempoolLimits {
+    fn default() -> Self {
+        MempoolLimits { max_transactions: 5_000, max_bytes: 32 * 1024 * 1024, max_per_sender: 64, replacement_bump_percent: 10 }
+    }
+}
 
 // La Mempool è la "Sala d'Attesa" delle transazioni
 pub struct Mempool {
-    // Usiamo una HashMap per trovare le transazioni velocemente tramite la loro firma
-    pub pending_txs: HashMap<String, Transaction>,
+    // Transazioni raggruppate per mittente e ordinate per nonce.
+    // Quelle con un nonce "futuro" restano in coda finché il buco non viene colmato.
+    pub pending_txs: HashMap<String, BTreeMap<u64, Transaction>>,
+    // ID transazione -> (mittente, nonce) delle transazioni in attesa
+    ids: HashMap<String, (String, u64)>,
+    rejected: RejectionLog,
+    // Commissione minima ogni 1000 byte (dalla configurazione del nodo)
+    pub min_relay_fee: u64,
+    pub limits: MempoolLimits,
+    bytes: u64, // Peso totale delle transazioni in attesa
 }
 
 impl Mempool {
     // Crea una Mempool vuota
-    pub fn new() -> Self {
+    pub fn new(min_relay_fee: u64, limits: MempoolLimits) -> Self {
         Mempool {
             pending_txs: HashMap::new(),
+            ids: HashMap::new(),
+            rejected: RejectionLog::default(),
+            min_relay_fee,
+            limits,
+            bytes: 0,
         }
     }
 
-    // Aggiunge una transazione SOLO se è valida
-    pub fn add_transaction(&mut self, tx: Transaction) -> bool {
-        // 1. Verifica crittografica (Dilithium)
-        if !tx.verify() {
+    // Aggiunge una transazione SOLO se è valida, altrimenti restituisce il motivo del rifiuto.
+    // `expected_nonce`, `registered_key` e `balance` vengono dallo stato della catena per il mittente,
+    // `next_height` è l'altezza del prossimo blocco.
+    pub fn add_transaction(&mut self, tx: Transaction, expected_nonce: u64, registered_key: Option<&str>, balance: u64, next_height: u64) -> Result<(), String> {
+        // Verifica crittografica (in cache se il nodo l'ha già fatta fuori dal runtime async).
+        // L'ID non copre le firme: chiunque può inviare una copia con la firma alterata, quindi
+        // questo rifiuto non si registra, altrimenti la transazione vera risulterebbe rifiutata.
+        if !verifier::verify(&tx, registered_key) {
             println!("   [MEMPOOL] ❌ Rifiutata transazione invalida (Firma errata)");
-            return false;
+            return Err("invalid signature".to_string());
+        }
+        self.evict_expired(next_height);
+        let id = tx.id();
+        let result = self.admit(id.clone(), tx, expected_nonce, balance, next_height);
+        match &result {
+            Ok(()) => self.rejected.forget(&id),
+            Err(reason) => self.rejected.record(id, reason.clone()),
+        }
+        result
+    }
+
+    // Regole di ammissione per una transazione con firma già verificata: i rifiuti valgono per il suo ID
+    fn admit(&mut self, id: String, tx: Transaction, expected_nonce: u64, balance: u64, next_height: u64) -> Result<(), String> {
+        // 1. Destinatario e regole del tipo di transazione
+        if !address::is_valid(&tx.receiver) {
+            println!("   [MEMPOOL] ❌ Rifiutata transazione con indirizzo destinatario non valido");
+            return Err(format!("invalid receiver address: {}", tx.receiver));
+        }
+        if let Err(e) = tx.validate() {
+            println!("   [MEMPOOL] ❌ Rifiutata transazione {} non valida: {}", tx.kind.name(), e);
+            return Err(e.to_string());
+        }
+
+        // 2. Scaduta: non entrerà più in nessun blocco. Se non è ancora valida resta in coda fino all'apertura della finestra.
+        if let Err(e) = tx.check_window(next_height, unix_time()) {
+            if tx.is_expired(next_height, unix_time()) {
+                println!("   [MEMPOOL] ❌ Rifiutata transazione scaduta: {}", e);
+                return Err(e.to_string());
+            }
+            println!("   [MEMPOOL] ⏳ {}: transazione in coda", e);
+        }
+
+        // 3. Commissione troppo bassa: non vale lo spazio che occupa
+        if tx.fee_rate() < self.min_relay_fee {
+            println!("   [MEMPOOL] ❌ Rifiutata transazione con commissione troppo bassa ({} < {})", tx.fee_rate(), self.min_relay_fee);
+            return Err(format!("fee rate {} is below the minimum {}", tx.fee_rate(), self.min_relay_fee));
+        }
+
+        // 4. Nonce già usato sulla catena: è un replay
+        if tx.nonce < expected_nonce {
+            println!("   [MEMPOOL] ❌ Rifiutata transazione con nonce vecchio ({} < {})", tx.nonce, expected_nonce);
+            return Err(format!("nonce {} already used (expected {})", tx.nonce, expected_nonce));
+        }
+
+        // 5. Il saldo deve coprire importo e commissione di tutte le transazioni in coda del mittente
+        // (quella con lo stesso nonce verrebbe sostituita, quindi non conta)
+        let committed = self.pending_txs.get(&tx.sender).map_or(0, |queue| {
+            queue.iter()
+                .filter(|(nonce, _)| **nonce != tx.nonce)
+                .fold(0u64, |total, (_, pending)| total.saturating_add(pending.amount).saturating_add(pending.fee))
+        });
+        let cost = tx.amount.saturating_add(tx.fee);
+        if committed.saturating_add(cost) > balance {
+            println!("   [MEMPOOL] ❌ Rifiutata transazione: saldo {} insufficiente ({} in coda + {})", balance, committed, cost);
+            return Err(format!("insufficient funds: balance {}, already pending {}, amount {} + fee {}", balance, committed, tx.amount, tx.fee));
+        }
+
+        // 6. Stesso mittente e stesso nonce già in attesa: la nuova la sostituisce solo se paga abbastanza di più (replace-by-fee)
+        let queued = self.pending_txs.get(&tx.sender).map_or(0, |queue| queue.len());
+        let replaced = match self.pending_txs.get(&tx.sender).and_then(|queue| queue.get(&tx.nonce)) {
+            Some(pending) => {
+                if pending.id() == id {
+                    println!("   [MEMPOOL] ❌ Rifiutata transazione duplicata (nonce {})", tx.nonce);
+                    return Err("transaction already pending".to_string());
+                }
+                let required = self.replacement_fee(pending, tx.size());
+                if tx.fee < required {
+                    println!("   [MEMPOOL] ❌ Rifiutata sostituzione del nonce {}: commissione {} < {}", tx.nonce, tx.fee, required);
+                    return Err(format!("a transaction with nonce {} is already pending: a replacement needs a fee of at least {}", tx.nonce, required));
+                }
+                self.remove(&tx.sender, tx.nonce)
+            }
+            None => None,
+        };
+
+        // 7. Un solo mittente non può occupare tutta la mempool (una sostituzione non aggiunge nulla)
+        if replaced.is_none() && queued >= self.limits.max_per_sender {
+            println!("   [MEMPOOL] ❌ Rifiutata transazione: {} ha già {} transazioni in attesa", tx.sender, queued);
+            return Err(format!("sender already has {} pending transactions (limit {})", queued, self.limits.max_per_sender));
+        }
+
+        // 8. Mempool piena: si entra solo al posto di transazioni meno redditizie, e solo se si è includibili
+        // subito (nonce senza buchi, finestra aperta): una coda di nonce futuri non scalza nessuno
+        let size = tx.size();
+        let ready = tx.nonce == self.next_nonce(&tx.sender, expected_nonce) && tx.check_window(next_height, unix_time()).is_ok();
+        if let Err(reason) = self.make_room(&tx, size, ready) {
+            // La transazione sostituita torna al suo posto
+            if let Some(previous) = replaced {
+                self.insert(previous.id(), previous);
+            }
+            return Err(reason);
+        }
+
+        if let Some(previous) = replaced {
+            println!("   [MEMPOOL] 🔁 Sostituita transazione nonce {} (commissione {} -> {})", tx.nonce, previous.fee, tx.fee);
+            self.rejected.record(previous.id(), format!("replaced by {} with fee {}", id, tx.fee));
+        } else if self.next_nonce(&tx.sender, expected_nonce) < tx.nonce {
+            println!("   [MEMPOOL] ⏳ Nonce futuro {} (atteso {}): transazione in coda finché il buco non viene colmato",
+                tx.nonce, self.next_nonce(&tx.sender, expected_nonce));
+        }
+        self.insert(id, tx);
+
+        println!("   [MEMPOOL] ✅ Transazione aggiunta. Totale in attesa: {}", self.len());
+        Ok(())
+    }
+
+    // Commissione minima per sostituire `pending` con una transazione di `size` byte. Deve salire di almeno
+    // `replacement_bump_percent` sia la commissione sia la commissione ogni 1000 byte (una sostituzione più
+    // pesante non deve pagare meno per byte), e la differenza deve pagare il rilancio dei nuovi byte a `min_relay_fee`.
+    pub fn replacement_fee(&self, pending: &Transaction, size: u64) -> u64 {
+        let bump = |value: u64| value.saturating_add(value.saturating_mul(self.limits.replacement_bump_percent).div_ceil(100).max(1));
+        let by_fee = bump(pending.fee);
+        let by_relay = pending.fee.saturating_add(self.min_relay_fee.saturating_mul(size).div_ceil(1000));
+        // fee_rate arrotonda per difetto: la commissione più bassa con fee * 1000 / size >= tasso richiesto
+        let by_rate = bump(pending.fee_rate()).saturating_mul(size).div_ceil(1000);
+        by_fee.max(by_relay).max(by_rate)
+    }
+
+    fn insert(&mut self, id: String, tx: Transaction) {
+        self.ids.insert(id, (tx.sender.clone(), tx.nonce));
+        self.bytes += tx.size();
+        self.pending_txs.entry(tx.sender.clone()).or_default().insert(tx.nonce, tx);
+    }
+
+    // Espelle le transazioni con commissione più bassa di `tx` finché non c'è posto, oppure non tocca nulla.
+    // Di ogni mittente si toglie sempre l'ultima in coda: chi resta non finisce dietro a un buco di nonce.
+    // Le transazioni del mittente di `tx` non si toccano (potrebbero precederla). Se `tx` non è `ready`
+    // per il prossimo blocco entra solo nello spazio libero.
+    fn make_room(&mut self, tx: &Transaction, size: u64, ready: bool) -> Result<(), String> {
+        let fee_rate = tx.fee_rate();
+        let mut count = self.len() + 1;
+        let mut bytes = self.bytes + size;
+        if count <= self.limits.max_transactions && bytes <= self.limits.max_bytes {
+            return Ok(());
+        }
+        if !ready {
+            println!("   [MEMPOOL] ❌ Rifiutata transazione: mempool piena e transazione non ancora includibile");
+            return Err("mempool is full: only transactions ready for the next block can displace pending ones".to_string());
+        }
+
+        // Per ogni altro mittente, le sue transazioni dall'ultima in coda alla prima
+        let tails: Vec<(&String, Vec<Candidate>)> = self.pending_txs.iter()
+            .filter(|(sender, _)| **sender != tx.sender)
+            .map(|(sender, queue)| {
+                (sender, queue.iter().rev().map(|(nonce, pending)| (*nonce, pending.fee_rate(), pending.size())).collect())
+            })
+            .collect();
+        let mut cursors = vec![0; tails.len()];
+        let mut heap: BinaryHeap<Reverse<(u64, usize)>> = tails.iter()
+            .enumerate()
+            .filter_map(|(i, (_, tail))| tail.first().map(|(_, rate, _)| Reverse((*rate, i))))
+            .collect();
+
+        let mut victims = Vec::new();
+        while count > self.limits.max_transactions || bytes > self.limits.max_bytes {
+            match heap.pop() {
+                Some(Reverse((victim_rate, i))) if victim_rate < fee_rate => {
+                    let (nonce, _, victim_size) = tails[i].1[cursors[i]];
+                    victims.push((tails[i].0.clone(), nonce));
+                    count -= 1;
+                    bytes -= victim_size;
+                    cursors[i] += 1;
+                    if let Some((_, next_rate, _)) = tails[i].1.get(cursors[i]) {
+                        heap.push(Reverse((*next_rate, i)));
+                    }
+                }
+                _ => {
+                    println!("   [MEMPOOL] ❌ Rifiutata transazione: mempool piena (commissione {})", fee_rate);
+                    return Err(format!("mempool is full: fee rate {} does not outbid any pending transaction", fee_rate));
+                }
+            }
+        }
+
+        for (sender, nonce) in victims {
+            if let Some(evicted) = self.remove(&sender, nonce) {
+                println!("   [MEMPOOL] 🗑️ Espulsa transazione di {} (nonce {}, commissione {}): mempool piena", sender, nonce, evicted.fee_rate());
+                self.rejected.record(evicted.id(), format!("evicted from a full mempool by better paying transactions (fee rate {})", evicted.fee_rate()));
+            }
+        }
+        Ok(())
+    }
+
+    fn remove(&mut self, sender: &str, nonce: u64) -> Option<Transaction> {
+        let queue = self.pending_txs.get_mut(sender)?;
+        let tx = queue.remove(&nonce)?;
+        if queue.is_empty() {
+            self.pending_txs.remove(sender);
+        }
+        self.ids.remove(&tx.id());
+        self.bytes = self.bytes.saturating_sub(tx.size());
+        Some(tx)
+    }
+
+    // Pending se in attesa, Rejected se rifiutata di recente; None se la mempool non la conosce
+    pub fn status(&self, id: &str) -> Option<TxStatus> {
+        if self.ids.contains_key(id) {
+            return Some(TxStatus::Pending);
+        }
+        self.rejected.reason(id).map(|reason| TxStatus::Rejected { reason: reason.to_string() })
+    }
+
+    // Prossimo nonce libero per `sender`: dopo quelli già in coda senza buchi
+    pub fn next_nonce(&self, sender: &str, expected_nonce: u64) -> u64 {
+        match self.pending_txs.get(sender) {
+            Some(queue) => queue.range(expected_nonce..)
+                .zip(expected_nonce..)
+                .take_while(|((nonce, _), expected)| *nonce == expected)
+                .count() as u64 + expected_nonce,
+            None => expected_nonce,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending_txs.values().map(|queue| queue.len()).sum()
+    }
+
+    // Peso totale in byte delle transazioni in attesa
+    pub fn size_bytes(&self) -> u64 {
+        self.bytes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending_txs.is_empty()
+    }
+
+    // Transazioni includibili subito in un blocco a `next_height`, dalla commissione più alta alla più bassa.
+    // Per ogni mittente si prende solo la sequenza di nonce senza buchi, sempre in ordine,
+    // fermandosi alla prima transazione fuori dalla sua finestra di validità.
+    pub fn ready_transactions<F>(&self, next_height: u64, expected_nonce: F) -> Vec<Transaction>
+    where
+        F: Fn(&str) -> u64,
+    {
+        let now = unix_time();
+        let mut chains: Vec<VecDeque<&Transaction>> = Vec::new();
+        for (sender, queue) in &self.pending_txs {
+            let first = expected_nonce(sender);
+            let contiguous: VecDeque<&Transaction> = queue.range(first..)
+                .zip(first..)
+                .take_while(|((nonce, tx), expected)| *nonce == expected && tx.check_window(next_height, now).is_ok())
+                .map(|((_, tx), _)| tx)
+                .collect();
+            if !contiguous.is_empty() {
+                chains.push(contiguous);
+            }
+        }
+
+        // Ogni mittente "compete" con la sua prossima transazione
+        let mut heap: BinaryHeap<(u64, usize)> = chains.iter()
+            .enumerate()
+            .map(|(i, chain)| (chain[0].fee_rate(), i))
+            .collect();
+
+        let mut ready = Vec::new();
+        while let Some((_, i)) = heap.pop() {
+            if let Some(tx) = chains[i].pop_front() {
+                ready.push(tx.clone());
+            }
+            if let Some(next) = chains[i].front() {
+                heap.push((next.fee_rate(), i));
+            }
+        }
+        ready
+    }
+
+    // Dopo un blocco toglie solo le transazioni incluse (nonce ormai consumato), quelle che il blocco
+    // ha reso invalide (`invalidation` ne dà il motivo, es. chiave ruotata) e quelle scadute.
+    // Tutte le altre restano in coda. `next_height` è l'altezza del prossimo blocco.
+    pub fn remove_stale<F, I>(&mut self, next_height: u64, expected_nonce: F, invalidation: I)
+    where
+        F: Fn(&str) -> u64,
+        I: Fn(&Transaction) -> Option<String>,
+    {
+        let mut invalidated = Vec::new();
+        self.pending_txs.retain(|sender, queue| {
+            let next = expected_nonce(sender);
+            queue.retain(|nonce, tx| {
+                if *nonce < next {
+                    return false;
+                }
+                match invalidation(tx) {
+                    Some(reason) => {
+                        invalidated.push((tx.id(), reason));
+                        false
+                    }
+                    None => true,
+                }
+            });
+            !queue.is_empty()
+        });
+        for (id, reason) in invalidated {
+            println!("   [MEMPOOL] 🗑️ Rimossa transazione non più valida: {}", reason);
+            self.rejected.record(id, reason);
+        }
+        self.evict_expired(next_height);
+        self.recount();
+    }
+
+    // Le transazioni oltre `valid_until` escono dalla mempool e risultano rifiutate
+    fn evict_expired(&mut self, next_height: u64) {
+        let now = unix_time();
+        let mut expired = Vec::new();
+        self.pending_txs.retain(|_, queue| {
+            queue.retain(|_, tx| {
+                let keep = !tx.is_expired(next_height, now);
+                if !keep {
+                    expired.push(tx.id());
+                }
+                keep
+            });
+            !queue.is_empty()
+        });
+        if expired.is_empty() {
+            return;
+        }
+        println!("   [MEMPOOL] ⌛ {} transazioni scadute rimosse", expired.len());
+        for id in expired {
+            self.rejected.record(id, "transaction expired before inclusion".to_string());
+        }
+        self.recount();
+    }
+
+    // Riallinea indice degli ID e peso totale dopo una rimozione in blocco
+    fn recount(&mut self) {
+        let pending = &self.pending_txs;
+        self.ids.retain(|_, (sender, nonce)| pending.get(sender.as_str()).is_some_and(|queue| queue.contains_key(nonce)));
+        self.bytes = pending.values().flat_map(|queue| queue.values()).map(|tx| tx.size()).sum();
+    }
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|elapsed| elapsed.as_secs()).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transaction::ValidityBound;
+    use crate::wallet::Wallet;
+
+    const BALANCE: u64 = 1_000_000;
+
+    fn mempool(max_transactions: usize) -> Mempool {
+        Mempool::new(0, MempoolLimits { max_transactions, ..MempoolLimits::default() })
+    }
+
+    fn transfer(wallet: &Wallet, nonce: u64, amount: u64, fee: u64) -> Transaction {
+        let receiver = address::from_account_key(&Wallet::new().public_key);
+        Transaction::new(wallet, receiver, amount, fee, nonce)
+    }
+
+    fn add(mempool: &mut Mempool, tx: &Transaction) -> Result<(), String> {
+        mempool.add_transaction(tx.clone(), 0, None, BALANCE, 1)
+    }
+
+    #[test]
+    fn future_nonce_waits_for_the_gap() {
+        let alice = Wallet::new();
+        let mut mempool = mempool(10);
+        let second = transfer(&alice, 1, 10, 1_000);
+        add(&mut mempool, &second).unwrap();
+        assert_eq!(mempool.status(&second.id()), Some(TxStatus::Pending));
+        assert_eq!(mempool.next_nonce(&alice.address(), 0), 0);
+        assert!(mempool.ready_transactions(1, |_| 0).is_empty());
+
+        let first = transfer(&alice, 0, 10, 1_000);
+        add(&mut mempool, &first).unwrap();
+        assert_eq!(mempool.next_nonce(&alice.address(), 0), 2);
+        let ready: Vec<u64> = mempool.ready_transactions(1, |_| 0).iter().map(|tx| tx.nonce).collect();
+        assert_eq!(ready, vec![0, 1]);
+    }
+
+    #[test]
+    fn used_nonce_is_a_replay() {
+        let alice = Wallet::new();
+        let mut mempool = mempool(10);
+        assert!(mempool.add_transaction(transfer(&alice, 2, 10, 1_000), 3, None, BALANCE, 1).is_err());
+    }
+
+    #[test]
+    fn fee_below_min_relay_is_rejected() {
+        let alice = Wallet::new();
+        let tx = transfer(&alice, 0, 10, 1_000);
+        let mut mempool = Mempool::new(tx.fee_rate() + 1, MempoolLimits::default());
+        assert!(add(&mut mempool, &tx).is_err());
+        mempool.min_relay_fee = tx.fee_rate();
+        add(&mut mempool, &tx).unwrap();
+    }
+
+    #[test]
+    fn ready_transactions_follow_fee_rate() {
+        let (alice, bob) = (Wallet::new(), Wallet::new());
+        let mut mempool = mempool(10);
+        let cheap = transfer(&alice, 0, 10, 1_000);
+        let rich = transfer(&bob, 0, 10, 5_000);
+        // Il nonce successivo di Alice paga di più, ma non può passare davanti al suo nonce 0
+        let follow_up = transfer(&alice, 1, 10, 9_000);
+        for tx in [&cheap, &rich, &follow_up] {
+            add(&mut mempool, tx).unwrap();
+        }
+        let ready: Vec<String> = mempool.ready_transactions(1, |_| 0).iter().map(|tx| tx.id()).collect();
+        assert_eq!(ready, vec![rich.id(), cheap.id(), follow_up.id()]);
+    }
+
+    #[test]
+    fn replace_by_fee_needs_the_bump() {
+        let alice = Wallet::new();
+        let mut mempool = mempool(10);
+        let first = transfer(&alice, 0, 10, 1_000);
+        add(&mut mempool, &first).unwrap();
+
+        let required = mempool.replacement_fee(&first, first.size());
+        assert!(add(&mut mempool, &transfer(&alice, 0, 20, required - 1)).is_err());
+        assert_eq!(mempool.status(&first.id()), Some(TxStatus::Pending));
+
+        let replacement = transfer(&alice, 0, 20, required);
+        add(&mut mempool, &replacement).unwrap();
+        assert_eq!(mempool.len(), 1);
+        assert_eq!(mempool.size_bytes(), replacement.size());
+        assert_eq!(mempool.status(&replacement.id()), Some(TxStatus::Pending));
+        assert!(matches!(mempool.status(&first.id()), Some(TxStatus::Rejected { .. })));
+    }
+
+    #[test]
+    fn pending_transactions_count_against_the_balance() {
+        let alice = Wallet::new();
+        let mut mempool = mempool(10);
+        mempool.add_transaction(transfer(&alice, 0, 1_000, 100), 0, None, 1_500, 1).unwrap();
+        assert!(mempool.add_transaction(transfer(&alice, 1, 500, 100), 0, None, 1_500, 1).is_err());
+        mempool.add_transaction(transfer(&alice, 1, 300, 100), 0, None, 1_500, 1).unwrap();
+    }
+
+    #[test]
+    fn full_mempool_evicts_the_cheapest_tail() {
+        let (alice, bob, carol) = (Wallet::new(), Wallet::new(), Wallet::new());
+        let mut mempool = mempool(3);
+        let alice_first = transfer(&alice, 0, 10, 1_000);
+        let alice_second = transfer(&alice, 1, 10, 9_000);
+        let bob_tx = transfer(&bob, 0, 10, 3_000);
+        for tx in [&alice_first, &alice_second, &bob_tx] {
+            add(&mut mempool, tx).unwrap();
+        }
+
+        // La commissione più bassa è la prima di Alice, ma toglierla lascerebbe un buco di nonce:
+        // dei mittenti si guarda solo l'ultima in coda
+        let carol_tx = transfer(&carol, 0, 10, 5_000);
+        add(&mut mempool, &carol_tx).unwrap();
+        assert_eq!(mempool.len(), 3);
+        assert!(matches!(mempool.status(&bob_tx.id()), Some(TxStatus::Rejected { .. })));
+        assert_eq!(mempool.status(&alice_first.id()), Some(TxStatus::Pending));
+        assert_eq!(mempool.status(&carol_tx.id()), Some(TxStatus::Pending));
+
+        // Chi paga meno di tutte le code non entra
+        assert!(add(&mut mempool, &transfer(&bob, 0, 10, 500)).is_err());
+        assert_eq!(mempool.len(), 3);
+    }
+
+    #[test]
+    fn future_nonces_do_not_displace_ready_transactions() {
+        let (alice, bob) = (Wallet::new(), Wallet::new());
+        let mut mempool = mempool(1);
+        let alice_tx = transfer(&alice, 0, 10, 1_000);
+        add(&mut mempool, &alice_tx).unwrap();
+
+        let gap = transfer(&bob, 5, 10, 50_000);
+        assert!(add(&mut mempool, &gap).is_err());
+        assert_eq!(mempool.status(&alice_tx.id()), Some(TxStatus::Pending));
+
+        add(&mut mempool, &transfer(&bob, 0, 10, 50_000)).unwrap();
+        assert!(matches!(mempool.status(&alice_tx.id()), Some(TxStatus::Rejected { .. })));
+    }
+
+    #[test]
+    fn remove_stale_keeps_what_is_still_valid() {
+        let (alice, bob) = (Wallet::new(), Wallet::new());
+        let mut mempool = mempool(10);
+        let included = transfer(&alice, 0, 10, 1_000);
+        let next = transfer(&alice, 1, 10, 1_000);
+        let invalid = transfer(&bob, 0, 10, 1_000);
+        for tx in [&included, &next, &invalid] {
+            add(&mut mempool, tx).unwrap();
         }
 
-        // 2. Se è valida, aggiungila alla lista
-        // Usiamo la firma come "ID" unico della transazione
-        self.pending_txs.insert(tx.signature.clone(), tx);
-        
-        println!("   [MEMPOOL] ✅ Transazione aggiunta. Totale in attesa: {}", self.pending_txs.len());
-        true
+        let alice_address = alice.address();
+        let invalid_id = invalid.id();
+        mempool.remove_stale(
+            2,
+            |sender| if sender == alice_address { 1 } else { 0 },
+            |tx| (tx.id() == invalid_id).then(|| "key rotated".to_string()),
+        );
+        assert_eq!(mempool.len(), 1);
+        assert_eq!(mempool.size_bytes(), next.size());
+        // Inclusa: non è più in attesa ma non è nemmeno un rifiuto
+        assert_eq!(mempool.status(&included.id()), None);
+        assert_eq!(mempool.status(&next.id()), Some(TxStatus::Pending));
+        assert_eq!(mempool.status(&invalid.id()), Some(TxStatus::Rejected { reason: "key rotated".to_string() }));
     }
 
-    // Pulisce la Mempool (si usa dopo aver creato un blocco)
-    pub fn clear(&mut self) {
-        self.pending_txs.clear();
+    #[test]
+    fn validity_windows_hold_and_expire() {
+        let (alice, bob) = (Wallet::new(), Wallet::new());
+        let mut mempool = mempool(10);
+        let mut later = Transaction::unsigned(alice.address(), bob.address(), 10, 1_000, 0);
+        later.valid_after = Some(ValidityBound::Height(10));
+        later.sign(&alice).unwrap();
+        let mut expiring = Transaction::unsigned(bob.address(), alice.address(), 10, 1_000, 0);
+        expiring.valid_until = Some(ValidityBound::Height(5));
+        expiring.sign(&bob).unwrap();
+        add(&mut mempool, &later).unwrap();
+        add(&mut mempool, &expiring).unwrap();
+
+        // Finestra non ancora aperta: resta in coda ma non entra nel blocco
+        let ready = mempool.ready_transactions(1, |_| 0);
+        assert_eq!(ready.iter().map(|tx| tx.id()).collect::<Vec<_>>(), vec![expiring.id()]);
+        let ready = mempool.ready_transactions(10, |_| 0);
+        assert_eq!(ready.iter().map(|tx| tx.id()).collect::<Vec<_>>(), vec![later.id()]);
+
+        mempool.remove_stale(6, |_| 0, |_| None);
+        assert_eq!(mempool.status(&later.id()), Some(TxStatus::Pending));
+        assert!(matches!(mempool.status(&expiring.id()), Some(TxStatus::Rejected { .. })));
+        assert!(mempool.add_transaction(expiring.clone(), 0, None, BALANCE, 6).is_err());
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,271 @@
+// Sparse Merkle Tree a 256 livelli: ogni chiave di stato ha una foglia in posizione sha3(chiave).
+// La radice riassume tutto lo stato; una prova sono gli "zii" lungo il percorso verso la foglia.
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Sha3_256};
+use sled::{Batch, Tree};
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::OnceLock;
+
+pub type Hash = [u8; 32];
+
+pub const DEPTH: usize = 256;
+
+// Modifiche ai nodi calcolate ma non ancora scritte su disco
+pub type NodeUpdates = HashMap<Vec<u8>, Hash>;
+
+pub fn hash_key(key: &str) -> Hash {
+    Sha3_256::digest(key.as_bytes()).into()
+}
+
+fn hash_leaf(path: &Hash, value: &[u8]) -> Hash {
+    let mut hasher = Sha3_256::new();
+    hasher.update([0u8]);
+    hasher.update(path);
+    hasher.update(value);
+    hasher.finalize().into()
+}
+
+fn hash_node(left: &Hash, right: &Hash) -> Hash {
+    let mut hasher = Sha3_256::new();
+    hasher.update([1u8]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+// Una foglia vuota vale zero; un valore vuoto equivale a chiave assente
+fn leaf_hash(path: &Hash, value: &[u8]) -> Hash {
+    if value.is_empty() {
+        [0u8; 32]
+    } else {
+        hash_leaf(path, value)
+    }
+}
+
+// Hash di un sottoalbero vuoto per ogni profondità (indice 256 = foglia)
+fn empty_hashes() -> &'static [Hash] {
+    static EMPTY: OnceLock<Vec<Hash>> = OnceLock::new();
+    EMPTY.get_or_init(|| {
+        let mut hashes = vec![[0u8; 32]; DEPTH + 1];
+        for depth in (0..DEPTH).rev() {
+            hashes[depth] = hash_node(&hashes[depth + 1], &hashes[depth + 1]);
+        }
+        hashes
+    })
+}
+
+fn bit(path: &Hash, index: usize) -> bool {
+    (path[index / 8] >> (7 - index % 8)) & 1 == 1
+}
+
+// Chiave del nodo: profondità + i primi `depth` bit del percorso (gli altri azzerati)
+fn node_key(depth: usize, path: &Hash) -> Vec<u8> {
+    let mut prefix = *path;
+    for i in depth..DEPTH {
+        prefix[i / 8] &= !(1 << (7 - i % 8));
+    }
+    let mut key = (depth as u16).to_be_bytes().to_vec();
+    key.extend_from_slice(&prefix);
+    key
+}
+
+fn sibling_path(path: &Hash, depth: usize) -> Hash {
+    // Il fratello del nodo a profondità `depth` differisce nel bit depth-1
+    let mut sibling = *path;
+    let i = depth - 1;
+    sibling[i / 8] ^= 1 << (7 - i % 8);
+    sibling
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ProofNode {
+    pub depth: u16,
+    pub hash: String,
+}
+
+// Prova di inclusione (o di assenza, se `value` è vuoto) di una chiave rispetto a `root`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct StateProof {
+    pub key: String,
+    pub value: String, // hex
+    pub root: String,
+    // Solo i fratelli non vuoti: gli altri valgono l'hash del sottoalbero vuoto
+    pub siblings: Vec<ProofNode>,
+}
+
+impl StateProof {
+    pub fn verify(&self) -> bool {
+        let (value, root) = match (hex::decode(&self.value), hex::decode(&self.root)) {
+            (Ok(value), Ok(root)) => (value, root),
+            _ => return false,
+        };
+        let mut siblings: HashMap<usize, Hash> = HashMap::new();
+        for node in &self.siblings {
+            match hex::decode(&node.hash).ok().and_then(|h| Hash::try_from(h).ok()) {
+                Some(hash) if (1..=DEPTH).contains(&(node.depth as usize)) => {
+                    siblings.insert(node.depth as usize, hash);
+                }
+                _ => return false,
+            }
+        }
+
+        let path = hash_key(&self.key);
+        let mut current = leaf_hash(&path, &value);
+        for depth in (1..=DEPTH).rev() {
+            let sibling = siblings.get(&depth).unwrap_or(&empty_hashes()[depth]);
+            current = if bit(&path, depth - 1) {
+                hash_node(sibling, &current)
+            } else {
+                hash_node(&current, sibling)
+            };
+        }
+        current.as_slice() == root.as_slice()
+    }
+}
+
+pub struct SparseMerkleTree {
+    nodes: Tree,
+}
+
+impl SparseMerkleTree {
+    pub fn new(nodes: Tree) -> Self {
+        SparseMerkleTree { nodes }
+    }
+
+    fn node(&self, updates: &NodeUpdates, depth: usize, path: &Hash) -> Result<Hash, Box<dyn Error>> {
+        let key = node_key(depth, path);
+        if let Some(hash) = updates.get(&key) {
+            return Ok(*hash);
+        }
+        match self.nodes.get(&key)? {
+            Some(bytes) => Ok(Hash::try_from(bytes.as_ref())?),
+            None => Ok(empty_hashes()[depth]),
+        }
+    }
+
+    pub fn root(&self) -> Result<Hash, Box<dyn Error>> {
+        self.node(&NodeUpdates::new(), 0, &[0u8; 32])
+    }
+
+    // Calcola la nuova radice dopo aver scritto `leaves` (chiave, valore), senza toccare il disco
+    pub fn prepare(&self, leaves: &[(String, Vec<u8>)]) -> Result<(Hash, NodeUpdates), Box<dyn Error>> {
+        let mut updates = NodeUpdates::new();
+        for (key, value) in leaves {
+            let path = hash_key(key);
+            let mut current = leaf_hash(&path, value);
+            updates.insert(node_key(DEPTH, &path), current);
+            for depth in (1..=DEPTH).rev() {
+                let sibling = self.node(&updates, depth, &sibling_path(&path, depth))?;
+                current = if bit(&path, depth - 1) {
+                    hash_node(&sibling, &current)
+                } else {
+                    hash_node(&current, &sibling)
+                };
+                updates.insert(node_key(depth - 1, &path), current);
+            }
+        }
+        let root = self.node(&updates, 0, &[0u8; 32])?;
+        Ok((root, updates))
+    }
+
+    pub fn commit(&self, updates: NodeUpdates) -> Result<(), Box<dyn Error>> {
+        self.nodes.apply_batch(Self::batch(updates))?;
+        Ok(())
+    }
+
+    // Scritture dei nodi aggiornati, da applicare nella stessa transazione del resto dello stato
+    pub fn batch(updates: NodeUpdates) -> Batch {
+        let mut batch = Batch::default();
+        for (key, hash) in updates {
+            batch.insert(key, &hash);
+        }
+        batch
+    }
+
+    pub fn nodes(&self) -> &Tree {
+        &self.nodes
+    }
+
+    pub fn prove(&self, key: &str, value: &[u8]) -> Result<StateProof, Box<dyn Error>> {
+        let path = hash_key(key);
+        let updates = NodeUpdates::new();
+        let mut siblings = Vec::new();
+        for depth in 1..=DEPTH {
+            let sibling = self.node(&updates, depth, &sibling_path(&path, depth))?;
+            if sibling != empty_hashes()[depth] {
+                siblings.push(ProofNode { depth: depth as u16, hash: hex::encode(sibling) });
+            }
+        }
+        Ok(StateProof {
+            key: key.to_string(),
+            value: hex::encode(value),
+            root: hex::encode(self.root()?),
+            siblings,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tree() -> SparseMerkleTree {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        SparseMerkleTree::new(db.open_tree("state_nodes").unwrap())
+    }
+
+    fn write(tree: &SparseMerkleTree, leaves: &[(&str, &[u8])]) {
+        let leaves: Vec<(String, Vec<u8>)> = leaves.iter().map(|(key, value)| (key.to_string(), value.to_vec())).collect();
+        let (root, updates) = tree.prepare(&leaves).unwrap();
+        tree.commit(updates).unwrap();
+        assert_eq!(tree.root().unwrap(), root);
+    }
+
+    #[test]
+    fn empty_tree_proves_absence() {
+        let tree = tree();
+        assert_eq!(tree.root().unwrap(), empty_hashes()[0]);
+        let proof = tree.prove("balance/adm0", &[]).unwrap();
+        assert!(proof.siblings.is_empty());
+        assert!(proof.verify());
+    }
+
+    #[test]
+    fn inclusion_and_absence_proofs() {
+        let tree = tree();
+        write(&tree, &[("balance/alice", b"100"), ("balance/bob", b"7")]);
+
+        assert!(tree.prove("balance/alice", b"100").unwrap().verify());
+        assert!(tree.prove("balance/bob", b"7").unwrap().verify());
+        // Valore sbagliato o chiave presente fatta passare per assente
+        assert!(!tree.prove("balance/alice", b"101").unwrap().verify());
+        assert!(!tree.prove("balance/alice", &[]).unwrap().verify());
+        // Chiave mai scritta: la prova di assenza regge
+        assert!(tree.prove("balance/carol", &[]).unwrap().verify());
+        assert!(!tree.prove("balance/carol", b"1").unwrap().verify());
+
+        // Una radice diversa invalida la prova
+        let mut proof = tree.prove("balance/carol", &[]).unwrap();
+        proof.root = hex::encode(empty_hashes()[0]);
+        assert!(!proof.verify());
+    }
+
+    #[test]
+    fn deleting_a_key_restores_the_previous_root() {
+        let tree = tree();
+        write(&tree, &[("balance/alice", b"100")]);
+        let before = tree.root().unwrap();
+        write(&tree, &[("document/abc", b"anchor")]);
+        assert_ne!(tree.root().unwrap(), before);
+
+        // `prepare` da solo non tocca il disco
+        let (root, _) = tree.prepare(&[("document/abc".to_string(), Vec::new())]).unwrap();
+        assert_eq!(root, before);
+        assert!(tree.prove("document/abc", b"anchor").unwrap().verify());
+
+        write(&tree, &[("document/abc", &[])]);
+        assert_eq!(tree.root().unwrap(), before);
+        assert!(tree.prove("document/abc", &[]).unwrap().verify());
+    }
+}
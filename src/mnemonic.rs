@@ -0,0 +1,58 @@
+// Frase di recupero (BIP-39) e derivazione deterministica delle chiavi Dilithium.
+// Da una sola frase si ricavano più conti, uno per ogni percorso "m/<a>/<b>/...".
+// Le chiavi lattice non supportano la derivazione pubblica: ogni livello è "hardened".
+use bip39::Mnemonic;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::OsRng;
+use sha3::{Digest, Sha3_512};
+use std::error::Error;
+use zeroize::{Zeroize, Zeroizing};
+
+pub const DEFAULT_PATH: &str = "m/0";
+
+// 32 byte di entropia = 24 parole
+const ENTROPY_BYTES: usize = 32;
+
+pub fn generate_phrase() -> Result<String, Box<dyn Error>> {
+    let mut entropy = [0u8; ENTROPY_BYTES];
+    OsRng.fill_bytes(&mut entropy);
+    let mnemonic = Mnemonic::from_entropy(&entropy)?;
+    entropy.zeroize();
+    Ok(mnemonic.to_string())
+}
+
+// Seed a 32 byte per il generatore di chiavi Dilithium, per la frase e il percorso dati
+pub fn derive_seed(phrase: &str, passphrase: &str, path: &str) -> Result<Zeroizing<[u8; 32]>, Box<dyn Error>> {
+    let mnemonic = Mnemonic::parse(phrase)?;
+    let bip39_seed = Zeroizing::new(mnemonic.to_seed(passphrase));
+
+    let mut node = Zeroizing::new([0u8; 64]);
+    let mut hasher = Sha3_512::new();
+    hasher.update(b"ADAMAS-MASTER");
+    hasher.update(bip39_seed.as_ref());
+    node.copy_from_slice(&hasher.finalize());
+
+    for index in parse_path(path)? {
+        let mut hasher = Sha3_512::new();
+        hasher.update(b"ADAMAS-CHILD");
+        hasher.update(node.as_ref());
+        hasher.update(index.to_be_bytes());
+        node.copy_from_slice(&hasher.finalize());
+    }
+
+    let mut seed = Zeroizing::new([0u8; 32]);
+    seed.copy_from_slice(&node[..32]);
+    Ok(seed)
+}
+
+// "m/0/3" -> [0, 3]
+pub fn parse_path(path: &str) -> Result<Vec<u32>, Box<dyn Error>> {
+    let mut parts = path.split('/');
+    if parts.next() != Some("m") {
+        return Err(format!("derivation path must start with 'm': {}", path).into());
+    }
+    parts
+        .map(|part| part.trim_end_matches('\'').parse::<u32>()
+            .map_err(|_| format!("invalid derivation path segment '{}' in {}", part, path).into()))
+        .collect()
+}
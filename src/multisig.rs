@@ -0,0 +1,79 @@
+// Conti multifirma m-di-n: l'indirizzo deriva da soglia + chiavi Dilithium dei firmatari
+use crate::address;
+use crate::signature;
+use crate::wallet::Wallet;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::error::Error;
+
+pub const MULTISIG_PREFIX: &str = "multisig/";
+pub const MAX_SIGNERS: usize = 16;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MultisigPolicy {
+    pub threshold: usize,
+    pub public_keys: Vec<String>, // Ordinate: l'ordine non deve cambiare l'indirizzo
+}
+
+// Firma di uno dei firmatari, identificato dalla posizione della sua chiave nella politica
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PartialSignature {
+    pub signer: usize,
+    pub signature: String,
+}
+
+impl MultisigPolicy {
+    pub fn new(threshold: usize, mut public_keys: Vec<String>) -> Result<Self, Box<dyn Error>> {
+        public_keys.sort();
+        public_keys.dedup();
+        if public_keys.is_empty() || public_keys.len() > MAX_SIGNERS {
+            return Err(format!("a multisig needs between 1 and {} distinct keys", MAX_SIGNERS).into());
+        }
+        if threshold == 0 || threshold > public_keys.len() {
+            return Err(format!("threshold must be between 1 and {}", public_keys.len()).into());
+        }
+        if public_keys.iter().any(|key| signature::algorithm_of(key).is_none()) {
+            return Err("public keys must carry a supported signature algorithm".into());
+        }
+        Ok(MultisigPolicy { threshold, public_keys })
+    }
+
+    // Forma canonica "multisig/<m>/<chiave1>,<chiave2>,...": è la "chiave" registrata on-chain
+    pub fn encode(&self) -> String {
+        format!("{}{}/{}", MULTISIG_PREFIX, self.threshold, self.public_keys.join(","))
+    }
+
+    // Accetta solo la forma canonica, così ogni politica ha un solo indirizzo
+    pub fn decode(encoded: &str) -> Option<Self> {
+        let (threshold, keys) = encoded.strip_prefix(MULTISIG_PREFIX)?.split_once('/')?;
+        let keys: Vec<String> = keys.split(',').map(|k| k.to_string()).collect();
+        let policy = MultisigPolicy::new(threshold.parse().ok()?, keys).ok()?;
+        if policy.encode() != encoded {
+            return None;
+        }
+        Some(policy)
+    }
+
+    pub fn address(&self) -> String {
+        address::from_account_key(&self.encode())
+    }
+
+    pub fn signer_index(&self, public_key: &str) -> Option<usize> {
+        self.public_keys.iter().position(|key| key == public_key)
+    }
+
+    // Servono almeno `threshold` firme valide di firmatari diversi
+    pub fn verify(&self, message: &str, signatures: &[PartialSignature]) -> bool {
+        let mut signers = HashSet::new();
+        for partial in signatures {
+            let key = match self.public_keys.get(partial.signer) {
+                Some(key) => key,
+                None => return false,
+            };
+            if !signers.insert(partial.signer) || !Wallet::verify(message, &partial.signature, key) {
+                return false;
+            }
+        }
+        signers.len() >= self.threshold
+    }
+}
@@ -1,3 +1,4 @@
+// Messaggi scambiati sul topic gossipsub della rete
 use serde::{Deserialize, Serialize};
 use crate::block::Block;
 use crate::transaction::Transaction;
@@ -5,5 +6,22 @@ use crate::transaction::Transaction;
 #[derive(Debug, Serialize, Deserialize)]
 pub enum NetworkMessage {
     Block(Block),             // Pacchetto contenente un Blocco
-    Transaction(Transaction), // Pacchetto contenente una Transazione (futuro)
-}
\ No newline at end of file
+    Transaction(Transaction), // Transazione entrata nella mempool di un nodo, da propagare a tutte le altre
+}
+
+impl NetworkMessage {
+    // I blocchi restano JSON "nudo" come prima: i nodi precedenti li leggono ancora.
+    // Le transazioni sono avvolte in {"Transaction": ...}, che quei nodi scartano.
+    pub fn encode(&self) -> Result<Vec<u8>, serde_json::Error> {
+        match self {
+            NetworkMessage::Block(block) => serde_json::to_vec(block),
+            NetworkMessage::Transaction(_) => serde_json::to_vec(self),
+        }
+    }
+
+    // Messaggio avvolto oppure blocco nudo
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        serde_json::from_slice(data).ok()
+            .or_else(|| serde_json::from_slice::<Block>(data).ok().map(NetworkMessage::Block))
+    }
+}
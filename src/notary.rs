@@ -0,0 +1,58 @@
+// Notarizzazione di documenti (bolle di consegna, certificati di laboratorio, ...): on-chain vanno solo
+// l'impronta SHA3-256 del file e qualche metadato, il file resta al cliente.
+// Il primo ancoraggio di un'impronta vale per sempre: lo stato lo registra sotto "document/<hash>",
+// quindi chiunque può ottenere una prova di inclusione (o di assenza) contro lo state_root.
+use crate::certification::{check_metadata, check_text, is_hash_hex};
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Sha3_256};
+use std::collections::BTreeMap;
+use std::error::Error;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DocumentRecord {
+    pub hash: String, // SHA3-256 (hex) del file
+    #[serde(default)]
+    pub name: String, // Es. "DDT-2024-118.pdf"
+    // Campi liberi del cliente (tipo di documento, laboratorio, ...), stesse regole dei record di certificazione
+    #[serde(default)]
+    pub metadata: BTreeMap<String, String>,
+}
+
+// Dove è stato ancorato un documento (la transazione è nel blocco `block_index`)
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DocumentAnchor {
+    pub block_index: u64,
+    pub block_timestamp: u128, // Millisecondi, come Block.timestamp
+    pub tx_id: String,
+    pub owner: String, // Mittente della transazione
+}
+
+pub fn hash_file(content: &[u8]) -> String {
+    hex::encode(Sha3_256::digest(content))
+}
+
+impl DocumentRecord {
+    pub fn new(hash: String) -> Self {
+        DocumentRecord { hash, name: String::new(), metadata: BTreeMap::new() }
+    }
+
+    pub fn validate(&self) -> Result<(), Box<dyn Error>> {
+        if !is_hash_hex(&self.hash) {
+            return Err(format!("document hash {} is not a lowercase hex SHA3-256 hash", self.hash).into());
+        }
+        check_text("name", &self.name, false)?;
+        check_metadata(&self.metadata)
+    }
+}
+
+impl DocumentAnchor {
+    // Valore della foglia di stato: altezza, timestamp e ID transazione, poi l'impronta del proprietario.
+    // Chi verifica una prova ricostruisce questi byte dai campi dell'ancoraggio.
+    pub fn leaf(&self) -> Vec<u8> {
+        let mut value = self.block_index.to_be_bytes().to_vec();
+        value.extend_from_slice(&self.block_timestamp.to_be_bytes());
+        value.extend_from_slice(self.tx_id.as_bytes());
+        value.extend_from_slice(&Sha3_256::digest(self.owner.as_bytes()));
+        value
+    }
+}
@@ -0,0 +1,285 @@
+// Firma offline per chiavi su macchine isolate (air-gapped):
+//   1. un nodo online prepara la transazione non firmata (nonce e chiave del conto dalla catena)
+//   2. la si porta sulla macchina offline come file JSON o come testo (QR code solo se è abbastanza corto)
+//   3. il Wallet offline la firma e produce la transazione firmata
+//   4. la si riporta online e la si invia al nodo (POST /transactions accetta JSON o testo)
+use crate::multisig::MultisigPolicy;
+use crate::signer::Signer;
+use crate::transaction::{Transaction, TransactionKind, ValidityBound};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Sha3_256};
+use std::error::Error;
+use std::fs;
+
+// Testo compatibile con la modalità alfanumerica dei QR code (solo A-Z, 0-9 e ':').
+// Il numero nel prefisso è la versione del layout bincode: va aumentato a ogni campo aggiunto o
+// modificato in UnsignedTransaction o Transaction, così un testo di un altro wallet non si decodifica male.
+pub const UNSIGNED_TEXT_PREFIX: &str = "ADMUTX2:";
+pub const SIGNED_TEXT_PREFIX: &str = "ADMSTX2:";
+// Prefissi senza versione: riconoscono anche i testi di un layout diverso per dare un errore chiaro
+const UNSIGNED_TEXT_KIND: &str = "ADMUTX";
+const SIGNED_TEXT_KIND: &str = "ADMSTX";
+const CHECKSUM_BYTES: usize = 4;
+
+// Caratteri alfanumerici in un QR code (versione 40, correzione L). Una transazione firmata con
+// Dilithium5 è ~14.600 caratteri: firmata si sposta come file, non come QR.
+pub const QR_ALPHANUMERIC_CAPACITY: usize = 4296;
+
+pub fn fits_qr(text: &str) -> bool {
+    text.len() <= QR_ALPHANUMERIC_CAPACITY
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct UnsignedTransaction {
+    pub sender: String,
+    pub receiver: String,
+    pub amount: u64,
+    pub fee: u64,
+    pub nonce: u64,
+    // Chiave registrata del conto (chiave singola o politica multisig), vuota alla prima spesa.
+    // Dice alla macchina offline con quale chiave firmare.
+    pub account_key: String,
+    #[serde(default)]
+    pub kind: TransactionKind,
+    #[serde(default)]
+    pub valid_after: Option<ValidityBound>,
+    #[serde(default)]
+    pub valid_until: Option<ValidityBound>,
+}
+
+impl UnsignedTransaction {
+    pub fn new(sender: String, receiver: String, amount: u64, fee: u64, nonce: u64, account_key: String) -> Self {
+        UnsignedTransaction { sender, receiver, amount, fee, nonce, account_key, kind: TransactionKind::Transfer, valid_after: None, valid_until: None }
+    }
+
+    // Rotazione: importo 0 verso sé stessi, la firma è della chiave attuale
+    pub fn new_key_rotation(sender: String, new_key: String, fee: u64, nonce: u64, account_key: String) -> Self {
+        UnsignedTransaction {
+            receiver: sender.clone(),
+            sender,
+            amount: 0,
+            fee,
+            nonce,
+            account_key,
+            kind: TransactionKind::KeyRotation { new_key },
+            valid_after: None,
+            valid_until: None,
+        }
+    }
+
+    // Conto multifirma: la politica dice chi firma. Serve alla prima spesa, quando il nodo non ha
+    // ancora una chiave registrata; se ce l'ha deve essere la stessa politica.
+    pub fn with_policy(mut self, policy: &MultisigPolicy) -> Result<Self, Box<dyn Error>> {
+        if policy.address() != self.sender {
+            return Err(format!("the policy belongs to {}, not to {}", policy.address(), self.sender).into());
+        }
+        if !self.account_key.is_empty() && self.account_key != policy.encode() {
+            return Err(format!("{} is registered with another key", self.sender).into());
+        }
+        self.account_key = policy.encode();
+        Ok(self)
+    }
+
+    // Firma sulla macchina offline. Per un multisig aggiunge la prima firma parziale:
+    // gli altri firmatari proseguono con `Transaction::add_signature` sul file firmato.
+    pub fn sign(&self, wallet: &dyn Signer) -> Result<Transaction, Box<dyn Error>> {
+        let mut tx = Transaction::unsigned(self.sender.clone(), self.receiver.clone(), self.amount, self.fee, self.nonce);
+        tx.kind = self.kind.clone();
+        tx.valid_after = self.valid_after;
+        tx.valid_until = self.valid_until;
+        match MultisigPolicy::decode(&self.account_key) {
+            Some(policy) => {
+                tx.public_key = policy.encode();
+                tx.add_signature(&policy, wallet)?;
+            }
+            None => {
+                // Alla prima spesa l'indirizzo deriva dalla chiave; dopo una rotazione conta la chiave registrata
+                let authorized = if self.account_key.is_empty() {
+                    wallet.address() == self.sender
+                } else {
+                    wallet.public_key() == self.account_key
+                };
+                if !authorized {
+                    return Err(format!("this wallet ({}) does not hold the key of {}", wallet.address(), self.sender).into());
+                }
+                tx.sign(wallet)?;
+            }
+        }
+        Ok(tx)
+    }
+
+    pub fn to_text(&self) -> Result<String, Box<dyn Error>> {
+        encode_text(UNSIGNED_TEXT_PREFIX, self)
+    }
+
+    pub fn from_text(text: &str) -> Result<Self, Box<dyn Error>> {
+        decode_text(UNSIGNED_TEXT_KIND, UNSIGNED_TEXT_PREFIX, text)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), Box<dyn Error>> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    // Accetta sia il file JSON sia il testo "ADMUTX2:..." (es. letto da un QR code)
+    pub fn load(path: &str) -> Result<Self, Box<dyn Error>> {
+        let content = fs::read_to_string(path)?;
+        if content.trim_start().starts_with(UNSIGNED_TEXT_KIND) {
+            return Self::from_text(&content);
+        }
+        Ok(serde_json::from_str(&content)?)
+    }
+}
+
+pub fn signed_to_text(tx: &Transaction) -> Result<String, Box<dyn Error>> {
+    encode_text(SIGNED_TEXT_PREFIX, tx)
+}
+
+pub fn signed_from_text(text: &str) -> Result<Transaction, Box<dyn Error>> {
+    decode_text(SIGNED_TEXT_KIND, SIGNED_TEXT_PREFIX, text)
+}
+
+// Transazione firmata da JSON o da testo "ADMSTX2:..."
+pub fn parse_signed(content: &str) -> Result<Transaction, Box<dyn Error>> {
+    if content.trim_start().starts_with(SIGNED_TEXT_KIND) {
+        return signed_from_text(content);
+    }
+    Ok(serde_json::from_str(content)?)
+}
+
+fn checksum(payload: &[u8]) -> [u8; CHECKSUM_BYTES] {
+    let digest = Sha3_256::digest(payload);
+    let mut out = [0u8; CHECKSUM_BYTES];
+    out.copy_from_slice(&digest[..CHECKSUM_BYTES]);
+    out
+}
+
+// prefisso + HEX(bincode) + HEX(checksum): un QR letto male o un file troncato non si decodifica
+fn encode_text<T: Serialize>(prefix: &str, value: &T) -> Result<String, Box<dyn Error>> {
+    let mut data = bincode::serialize(value)?;
+    let sum = checksum(&data);
+    data.extend_from_slice(&sum);
+    Ok(format!("{}{}", prefix, hex::encode_upper(data)))
+}
+
+fn decode_text<T: DeserializeOwned>(kind: &str, prefix: &str, text: &str) -> Result<T, Box<dyn Error>> {
+    let text = text.trim();
+    let body = match text.strip_prefix(prefix) {
+        Some(body) => body,
+        None if text.starts_with(kind) => {
+            let version = text.split(':').next().unwrap_or(kind);
+            return Err(format!("{} text uses another transaction layout (this wallet reads {}): prepare it again with this version", version, prefix).into());
+        }
+        None => return Err(format!("expected text starting with {}", prefix).into()),
+    };
+    let data = hex::decode(body)?;
+    if data.len() < CHECKSUM_BYTES {
+        return Err("encoded transaction is too short".into());
+    }
+    let (payload, sum) = data.split_at(data.len() - CHECKSUM_BYTES);
+    if sum != checksum(payload) {
+        return Err("checksum mismatch: the encoded transaction is corrupted".into());
+    }
+    Ok(bincode::deserialize(payload)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wallet::Wallet;
+
+    fn unsigned(wallet: &Wallet) -> UnsignedTransaction {
+        let receiver = Wallet::new().address();
+        let mut unsigned = UnsignedTransaction::new(wallet.address(), receiver, 250, 1_000, 3, String::new());
+        unsigned.valid_until = Some(ValidityBound::Height(90));
+        unsigned
+    }
+
+    // Un carattere esadecimale cambiato nel corpo (il QR letto male)
+    fn corrupt(text: &str, position: usize) -> String {
+        let mut chars: Vec<char> = text.chars().collect();
+        chars[position] = if chars[position] == 'A' { 'B' } else { 'A' };
+        chars.into_iter().collect()
+    }
+
+    #[test]
+    fn unsigned_text_round_trip() {
+        let wallet = Wallet::new();
+        let unsigned = unsigned(&wallet);
+        let text = unsigned.to_text().unwrap();
+        assert!(text.starts_with(UNSIGNED_TEXT_PREFIX));
+        assert!(text[UNSIGNED_TEXT_PREFIX.len()..].chars().all(|c| c.is_ascii_digit() || c.is_ascii_uppercase()));
+        assert!(fits_qr(&text));
+        assert_eq!(UnsignedTransaction::from_text(&format!("  {}\n", text)).unwrap(), unsigned);
+    }
+
+    #[test]
+    fn signed_text_round_trip() {
+        let wallet = Wallet::new();
+        let tx = unsigned(&wallet).sign(&wallet).unwrap();
+        assert!(tx.verify(None));
+        let text = signed_to_text(&tx).unwrap();
+        assert!(text.starts_with(SIGNED_TEXT_PREFIX));
+        // Dilithium-5 firmata non sta in un QR code
+        assert!(!fits_qr(&text));
+        let decoded = parse_signed(&text).unwrap();
+        assert_eq!(decoded.id(), tx.id());
+        assert_eq!(decoded.signature, tx.signature);
+        assert!(decoded.verify(None));
+    }
+
+    #[test]
+    fn corrupted_or_foreign_text_is_rejected() {
+        let wallet = Wallet::new();
+        let text = unsigned(&wallet).to_text().unwrap();
+        for position in [UNSIGNED_TEXT_PREFIX.len(), text.len() / 2, text.len() - 1] {
+            let error = UnsignedTransaction::from_text(&corrupt(&text, position)).unwrap_err();
+            assert!(error.to_string().starts_with("checksum mismatch"), "{}", error);
+        }
+        assert!(UnsignedTransaction::from_text(&text[..text.len() - 10]).is_err());
+
+        let old = text.replacen(UNSIGNED_TEXT_PREFIX, "ADMUTX1:", 1);
+        assert!(UnsignedTransaction::from_text(&old).unwrap_err().to_string().contains("another transaction layout"));
+        // Un testo firmato non passa per uno da firmare
+        assert!(UnsignedTransaction::from_text(&text.replacen("ADMUTX", "ADMSTX", 1)).is_err());
+    }
+
+    #[test]
+    fn offline_wallet_must_hold_the_key() {
+        let (wallet, stranger) = (Wallet::new(), Wallet::new());
+        let unsigned = unsigned(&wallet);
+        assert!(unsigned.sign(&stranger).is_err());
+    }
+
+    #[test]
+    fn fresh_multisig_build_and_cosign() {
+        let signers = [Wallet::new(), Wallet::new(), Wallet::new()];
+        let policy = MultisigPolicy::new(2, signers.iter().map(|wallet| wallet.public_key.clone()).collect()).unwrap();
+        let sender = policy.address();
+        let receiver = Wallet::new().address();
+
+        // Prima spesa: il nodo non ha ancora una chiave registrata per il conto
+        let unsigned = UnsignedTransaction::new(sender.clone(), receiver.clone(), 10, 1_000, 0, String::new());
+        assert!(unsigned.sign(&signers[0]).is_err());
+        let unsigned = unsigned.with_policy(&policy).unwrap();
+        let unsigned = UnsignedTransaction::from_text(&unsigned.to_text().unwrap()).unwrap();
+
+        let mut tx = unsigned.sign(&signers[2]).unwrap();
+        assert_eq!(tx.public_key, policy.encode());
+        assert!(!tx.verify(None));
+        // Il file firmato passa al secondo firmatario (cosign)
+        let mut cosigned = parse_signed(&serde_json::to_string(&tx).unwrap()).unwrap();
+        cosigned.add_signature(&policy, &signers[0]).unwrap();
+        assert!(cosigned.verify(None));
+        assert!(cosigned.verify(Some(&policy.encode())));
+        // Due volte lo stesso firmatario non raggiunge la soglia
+        tx.add_signature(&policy, &signers[2]).unwrap();
+        assert!(!tx.verify(None));
+
+        // Politica di un altro conto o chiave registrata diversa
+        let other = MultisigPolicy::new(1, vec![signers[1].public_key.clone()]).unwrap();
+        assert!(UnsignedTransaction::new(sender.clone(), receiver.clone(), 10, 1_000, 0, String::new()).with_policy(&other).is_err());
+        assert!(UnsignedTransaction::new(sender, receiver, 10, 1_000, 0, other.encode()).with_policy(&policy).is_err());
+    }
+}
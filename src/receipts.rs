@@ -0,0 +1,113 @@
+// Ricevute delle transazioni: in quale blocco è finita una transazione, oppure perché è stata rifiutata.
+// Le ricevute dei blocchi stanno su disco (vedi BlockchainDB); i rifiuti solo in RAM, non sono consenso.
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+
+// Conferme dopo cui una transazione si considera definitiva
+pub const FINALITY_DEPTH: u64 = 6;
+
+// Rifiuti ricordati per `GET /tx/{id}`: i più vecchi vengono dimenticati
+pub const MAX_REJECTIONS: usize = 10_000;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Receipt {
+    pub block_index: u64,
+    pub block_hash: String,
+    pub position: u32, // Posizione della transazione nel blocco
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum TxStatus {
+    Pending,
+    Included { height: u64, block_hash: String, confirmations: u64 },
+    Finalized { height: u64, block_hash: String, confirmations: u64 },
+    Rejected { reason: String },
+    Unknown,
+}
+
+impl TxStatus {
+    // Il blocco che contiene la transazione conta come prima conferma
+    pub fn from_receipt(receipt: &Receipt, tip: u64) -> Self {
+        let height = receipt.block_index;
+        let block_hash = receipt.block_hash.clone();
+        let confirmations = tip.saturating_sub(height) + 1;
+        if confirmations >= FINALITY_DEPTH {
+            TxStatus::Finalized { height, block_hash, confirmations }
+        } else {
+            TxStatus::Included { height, block_hash, confirmations }
+        }
+    }
+}
+
+// Motivi di rifiuto per ID di transazione, con espulsione FIFO come la cache delle firme
+#[derive(Default)]
+pub struct RejectionLog {
+    reasons: HashMap<String, String>,
+    order: VecDeque<String>,
+}
+
+impl RejectionLog {
+    pub fn record(&mut self, id: String, reason: String) {
+        if self.reasons.insert(id.clone(), reason).is_none() {
+            self.order.push_back(id);
+        }
+        while self.order.len() > MAX_REJECTIONS {
+            if let Some(oldest) = self.order.pop_front() {
+                self.reasons.remove(&oldest);
+            }
+        }
+    }
+
+    // Una transazione accettata dopo un rifiuto (es. nonce ora corretto) non è più "rifiutata"
+    pub fn forget(&mut self, id: &str) {
+        if self.reasons.remove(id).is_some() {
+            self.order.retain(|known| known != id);
+        }
+    }
+
+    pub fn reason(&self, id: &str) -> Option<&str> {
+        self.reasons.get(id).map(|reason| reason.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn included_then_finalized() {
+        let receipt = Receipt { block_index: 10, block_hash: "ab".to_string(), position: 0 };
+        let included = |confirmations| TxStatus::Included { height: 10, block_hash: "ab".to_string(), confirmations };
+        assert_eq!(TxStatus::from_receipt(&receipt, 10), included(1));
+        assert_eq!(TxStatus::from_receipt(&receipt, 10 + FINALITY_DEPTH - 2), included(FINALITY_DEPTH - 1));
+        assert_eq!(
+            TxStatus::from_receipt(&receipt, 10 + FINALITY_DEPTH - 1),
+            TxStatus::Finalized { height: 10, block_hash: "ab".to_string(), confirmations: FINALITY_DEPTH }
+        );
+        // Punta più vecchia del blocco (es. risposta durante una riorganizzazione): mai meno di una conferma
+        assert_eq!(TxStatus::from_receipt(&receipt, 3), included(1));
+    }
+
+    #[test]
+    fn rejection_log_is_bounded() {
+        let mut log = RejectionLog::default();
+        for i in 0..MAX_REJECTIONS + 5 {
+            log.record(format!("tx{}", i), "nonce already used".to_string());
+        }
+        assert_eq!(log.order.len(), MAX_REJECTIONS);
+        assert_eq!(log.reasons.len(), MAX_REJECTIONS);
+        assert_eq!(log.reason("tx0"), None);
+        assert_eq!(log.reason("tx4"), None);
+        assert_eq!(log.reason("tx5"), Some("nonce already used"));
+
+        // Un nuovo motivo per lo stesso ID lo aggiorna senza occupare un altro posto
+        log.record("tx5".to_string(), "expired".to_string());
+        assert_eq!(log.order.len(), MAX_REJECTIONS);
+        assert_eq!(log.reason("tx5"), Some("expired"));
+
+        log.forget("tx5");
+        assert_eq!(log.reason("tx5"), None);
+        assert_eq!(log.order.len(), MAX_REJECTIONS - 1);
+    }
+}
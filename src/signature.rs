@@ -0,0 +1,439 @@
+// Schemi di firma post-quantum intercambiabili.
+// Chiavi e firme portano in testa un byte con l'ID dell'algoritmo: hex(id ‖ byte),
+// così un nodo sa sempre come verificarle e la catena può cambiare algoritmo in futuro.
+use pqcrypto_traits::sign::{DetachedSignature as _, PublicKey as _, SecretKey as _};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Algorithm {
+    Dilithium2,
+    Dilithium3,
+    Dilithium5,
+    Falcon512,
+    Falcon1024,
+    SphincsShake256f,
+    HybridEd25519Dilithium5,
+}
+
+pub const DEFAULT_ALGORITHM: Algorithm = Algorithm::Dilithium5;
+
+// Esegue `$body` con `$m` = modulo pqcrypto dell'algoritmo scelto
+macro_rules! with_scheme {
+    ($algorithm:expr, $m:ident => $body:expr) => {
+        match $algorithm {
+            Algorithm::Dilithium2 => { use pqcrypto_dilithium::dilithium2 as $m; $body }
+            Algorithm::Dilithium3 => { use pqcrypto_dilithium::dilithium3 as $m; $body }
+            Algorithm::Dilithium5 => { use pqcrypto_dilithium::dilithium5 as $m; $body }
+            Algorithm::Falcon512 => { use pqcrypto_falcon::falcon512 as $m; $body }
+            Algorithm::Falcon1024 => { use pqcrypto_falcon::falcon1024 as $m; $body }
+            Algorithm::SphincsShake256f => { use pqcrypto_sphincsplus::sphincsshake256fsimple as $m; $body }
+            Algorithm::HybridEd25519Dilithium5 => { use self::hybrid as $m; $body }
+        }
+    };
+}
+
+impl Algorithm {
+    pub const ALL: [Algorithm; 7] = [
+        Algorithm::Dilithium2,
+        Algorithm::Dilithium3,
+        Algorithm::Dilithium5,
+        Algorithm::Falcon512,
+        Algorithm::Falcon1024,
+        Algorithm::SphincsShake256f,
+        Algorithm::HybridEd25519Dilithium5,
+    ];
+
+    // ID on-chain: non cambiarli mai, sono dentro chiavi e indirizzi già registrati
+    pub fn id(self) -> u8 {
+        match self {
+            Algorithm::Dilithium2 => 0x02,
+            Algorithm::Dilithium3 => 0x03,
+            Algorithm::Dilithium5 => 0x05,
+            Algorithm::Falcon512 => 0x10,
+            Algorithm::Falcon1024 => 0x11,
+            Algorithm::SphincsShake256f => 0x20,
+            Algorithm::HybridEd25519Dilithium5 => 0x85,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        Self::ALL.into_iter().find(|algorithm| algorithm.id() == id)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Algorithm::Dilithium2 => "dilithium2",
+            Algorithm::Dilithium3 => "dilithium3",
+            Algorithm::Dilithium5 => "dilithium5",
+            Algorithm::Falcon512 => "falcon512",
+            Algorithm::Falcon1024 => "falcon1024",
+            Algorithm::SphincsShake256f => "sphincs-shake256f",
+            Algorithm::HybridEd25519Dilithium5 => "ed25519+dilithium5",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|algorithm| algorithm.name() == name)
+    }
+
+    // Restituisce (chiave pubblica, chiave segreta) senza tag
+    pub fn keypair(self) -> (Vec<u8>, Vec<u8>) {
+        with_scheme!(self, m => {
+            let (pk, sk) = m::keypair();
+            (pk.as_bytes().to_vec(), sk.as_bytes().to_vec())
+        })
+    }
+
+    pub fn is_valid_secret_key(self, secret_key: &[u8]) -> bool {
+        with_scheme!(self, m => m::SecretKey::from_bytes(secret_key).is_ok())
+    }
+
+    pub fn is_valid_public_key(self, public_key: &[u8]) -> bool {
+        with_scheme!(self, m => m::PublicKey::from_bytes(public_key).is_ok())
+    }
+
+    pub fn sign(self, message: &[u8], secret_key: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+        with_scheme!(self, m => {
+            let sk = m::SecretKey::from_bytes(secret_key)
+                .map_err(|_| format!("invalid {} secret key", self.name()))?;
+            Ok(m::detached_sign(message, &sk).as_bytes().to_vec())
+        })
+    }
+
+    pub fn verify(self, message: &[u8], signature: &[u8], public_key: &[u8]) -> bool {
+        with_scheme!(self, m => {
+            let (pk, sig) = match (m::PublicKey::from_bytes(public_key), m::DetachedSignature::from_bytes(signature)) {
+                (Ok(pk), Ok(sig)) => (pk, sig),
+                _ => return false,
+            };
+            m::verify_detached_signature(&sig, message, &pk).is_ok()
+        })
+    }
+}
+
+// Modalità ibrida: una firma classica (Ed25519) e una post-quantum (Dilithium-5) sullo stesso
+// messaggio. Chiavi e firme sono la concatenazione delle due parti; valida solo se lo sono entrambe.
+// Stessa interfaccia dei moduli pqcrypto, così passa per `with_scheme!` come gli altri schemi.
+mod hybrid {
+    use chacha20poly1305::aead::rand_core::RngCore;
+    use chacha20poly1305::aead::OsRng;
+    use ed25519_dalek::{Signer, SigningKey, Verifier, VerifyingKey};
+    use pqcrypto_dilithium::dilithium5;
+    use pqcrypto_traits::sign::{DetachedSignature as _, PublicKey as _, SecretKey as _};
+    use zeroize::{Zeroize, Zeroizing};
+
+    const ED25519_KEY_BYTES: usize = 32;
+    const ED25519_SIGNATURE_BYTES: usize = 64;
+
+    pub struct PublicKey(Vec<u8>);
+    pub struct SecretKey(Zeroizing<Vec<u8>>);
+    pub struct DetachedSignature(Vec<u8>);
+
+    fn classic_public(bytes: &[u8]) -> Option<VerifyingKey> {
+        VerifyingKey::from_bytes(bytes.get(..ED25519_KEY_BYTES)?.try_into().ok()?).ok()
+    }
+
+    fn classic_secret(bytes: &[u8]) -> Option<SigningKey> {
+        Some(SigningKey::from_bytes(bytes.get(..ED25519_KEY_BYTES)?.try_into().ok()?))
+    }
+
+    impl PublicKey {
+        pub fn from_bytes(bytes: &[u8]) -> Result<Self, ()> {
+            classic_public(bytes).ok_or(())?;
+            dilithium5::PublicKey::from_bytes(&bytes[ED25519_KEY_BYTES..]).map_err(|_| ())?;
+            Ok(PublicKey(bytes.to_vec()))
+        }
+
+        pub fn as_bytes(&self) -> &[u8] {
+            &self.0
+        }
+    }
+
+    impl SecretKey {
+        pub fn from_bytes(bytes: &[u8]) -> Result<Self, ()> {
+            classic_secret(bytes).ok_or(())?;
+            dilithium5::SecretKey::from_bytes(&bytes[ED25519_KEY_BYTES..]).map_err(|_| ())?;
+            Ok(SecretKey(Zeroizing::new(bytes.to_vec())))
+        }
+
+        pub fn as_bytes(&self) -> &[u8] {
+            &self.0
+        }
+    }
+
+    impl DetachedSignature {
+        pub fn from_bytes(bytes: &[u8]) -> Result<Self, ()> {
+            if bytes.len() <= ED25519_SIGNATURE_BYTES {
+                return Err(());
+            }
+            dilithium5::DetachedSignature::from_bytes(&bytes[ED25519_SIGNATURE_BYTES..]).map_err(|_| ())?;
+            Ok(DetachedSignature(bytes.to_vec()))
+        }
+
+        pub fn as_bytes(&self) -> &[u8] {
+            &self.0
+        }
+    }
+
+    pub fn keypair() -> (PublicKey, SecretKey) {
+        let mut seed = [0u8; ED25519_KEY_BYTES];
+        OsRng.fill_bytes(&mut seed);
+        let classic = SigningKey::from_bytes(&seed);
+        let (pq_public, pq_secret) = dilithium5::keypair();
+
+        let mut public_key = classic.verifying_key().to_bytes().to_vec();
+        public_key.extend_from_slice(pq_public.as_bytes());
+        // Capacità esatta: nessuna riallocazione che lasci copie della chiave in memoria
+        let mut secret_key = Zeroizing::new(Vec::with_capacity(ED25519_KEY_BYTES + pq_secret.as_bytes().len()));
+        secret_key.extend_from_slice(&seed);
+        secret_key.extend_from_slice(pq_secret.as_bytes());
+        seed.zeroize();
+        (PublicKey(public_key), SecretKey(secret_key))
+    }
+
+    pub fn detached_sign(message: &[u8], secret_key: &SecretKey) -> DetachedSignature {
+        // SecretKey::from_bytes ha già controllato entrambe le metà
+        let classic = classic_secret(&secret_key.0).expect("Key Error");
+        let pq_secret = dilithium5::SecretKey::from_bytes(&secret_key.0[ED25519_KEY_BYTES..]).expect("Key Error");
+
+        let mut signature = classic.sign(message).to_bytes().to_vec();
+        signature.extend_from_slice(dilithium5::detached_sign(message, &pq_secret).as_bytes());
+        DetachedSignature(signature)
+    }
+
+    pub fn verify_detached_signature(signature: &DetachedSignature, message: &[u8], public_key: &PublicKey) -> Result<(), ()> {
+        let classic_key = classic_public(&public_key.0).ok_or(())?;
+        let classic_signature = ed25519_dalek::Signature::from_slice(&signature.0[..ED25519_SIGNATURE_BYTES]).map_err(|_| ())?;
+        classic_key.verify(message, &classic_signature).map_err(|_| ())?;
+
+        let pq_key = dilithium5::PublicKey::from_bytes(&public_key.0[ED25519_KEY_BYTES..]).map_err(|_| ())?;
+        let pq_signature = dilithium5::DetachedSignature::from_bytes(&signature.0[ED25519_SIGNATURE_BYTES..]).map_err(|_| ())?;
+        dilithium5::verify_detached_signature(&pq_signature, message, &pq_key).map_err(|_| ())
+    }
+}
+
+// Chiavi Dilithium-5 deterministiche (frase di recupero) con lo stesso codice PQClean che firma e verifica:
+// è crypto_sign_keypair di sign.c con il seed al posto di randombytes, chiamando le funzioni interne
+// della libreria compilata da pqcrypto-dilithium. Costanti e firme C sono quelle della versione fissata
+// in Cargo.toml (=0.5.0): aggiornando la dipendenza vanno ricontrollate, e il test vector in wallet.rs lo segnala.
+pub mod dilithium5_seeded {
+    use sha3::digest::{ExtendableOutput, Update, XofReader};
+    use sha3::Shake256;
+    use zeroize::{Zeroize, Zeroizing};
+
+    const N: usize = 256;
+    const K: usize = 8;
+    const L: usize = 7;
+    const SEEDBYTES: usize = 32;
+    const CRHBYTES: usize = 64;
+    const TRBYTES: usize = 64;
+    const PUBLICKEYBYTES: usize = 2592;
+    const SECRETKEYBYTES: usize = 4896;
+
+    #[repr(C)]
+    #[derive(Clone, Copy)]
+    struct Poly {
+        coeffs: [i32; N],
+    }
+
+    #[repr(C)]
+    #[derive(Clone, Copy)]
+    struct PolyVecL {
+        vec: [Poly; L],
+    }
+
+    #[repr(C)]
+    #[derive(Clone, Copy)]
+    struct PolyVecK {
+        vec: [Poly; K],
+    }
+
+    // Le strutture sono copiate da poly.h/polyvec.h di PQClean: se la versione di pqcrypto-dilithium
+    // cambiasse dimensioni o layout la compilazione si ferma qui, invece di produrre chiavi sbagliate
+    const _: () = {
+        assert!(std::mem::size_of::<Poly>() == N * 4);
+        assert!(std::mem::align_of::<Poly>() == 4);
+        assert!(std::mem::size_of::<PolyVecL>() == L * N * 4);
+        assert!(std::mem::size_of::<PolyVecK>() == K * N * 4);
+        assert!(PUBLICKEYBYTES == pqcrypto_dilithium::dilithium5::public_key_bytes());
+        assert!(SECRETKEYBYTES == pqcrypto_dilithium::dilithium5::secret_key_bytes());
+    };
+
+    const ZERO_L: PolyVecL = PolyVecL { vec: [Poly { coeffs: [0; N] }; L] };
+    const ZERO_K: PolyVecK = PolyVecK { vec: [Poly { coeffs: [0; N] }; K] };
+
+    // Firme da polyvec.h e packing.h di PQClean (dilithium5/clean), la versione compilata da pqcrypto-dilithium 0.5.0
+    extern "C" {
+        fn PQCLEAN_DILITHIUM5_CLEAN_polyvec_matrix_expand(mat: *mut PolyVecL, rho: *const u8);
+        fn PQCLEAN_DILITHIUM5_CLEAN_polyvecl_uniform_eta(v: *mut PolyVecL, seed: *const u8, nonce: u16);
+        fn PQCLEAN_DILITHIUM5_CLEAN_polyveck_uniform_eta(v: *mut PolyVecK, seed: *const u8, nonce: u16);
+        fn PQCLEAN_DILITHIUM5_CLEAN_polyvecl_ntt(v: *mut PolyVecL);
+        fn PQCLEAN_DILITHIUM5_CLEAN_polyvec_matrix_pointwise_montgomery(t: *mut PolyVecK, mat: *const PolyVecL, v: *const PolyVecL);
+        fn PQCLEAN_DILITHIUM5_CLEAN_polyveck_reduce(v: *mut PolyVecK);
+        fn PQCLEAN_DILITHIUM5_CLEAN_polyveck_invntt_tomont(v: *mut PolyVecK);
+        fn PQCLEAN_DILITHIUM5_CLEAN_polyveck_add(w: *mut PolyVecK, u: *const PolyVecK, v: *const PolyVecK);
+        fn PQCLEAN_DILITHIUM5_CLEAN_polyveck_caddq(v: *mut PolyVecK);
+        fn PQCLEAN_DILITHIUM5_CLEAN_polyveck_power2round(v1: *mut PolyVecK, v0: *mut PolyVecK, v: *const PolyVecK);
+        fn PQCLEAN_DILITHIUM5_CLEAN_pack_pk(pk: *mut u8, rho: *const u8, t1: *const PolyVecK);
+        fn PQCLEAN_DILITHIUM5_CLEAN_pack_sk(sk: *mut u8, rho: *const u8, tr: *const u8, key: *const u8, t0: *const PolyVecK, s1: *const PolyVecL, s2: *const PolyVecK);
+    }
+
+    fn shake256(input: &[u8], output: &mut [u8]) {
+        let mut hasher = Shake256::default();
+        hasher.update(input);
+        hasher.finalize_xof().read(output);
+    }
+
+    // (chiave pubblica, chiave segreta) nei formati di pqcrypto_dilithium::dilithium5
+    pub fn keypair(seed: &[u8; SEEDBYTES]) -> (Vec<u8>, Zeroizing<Vec<u8>>) {
+        // rho ‖ rhoprime ‖ key = SHAKE256(seed)
+        let mut seedbuf = Zeroizing::new([0u8; 2 * SEEDBYTES + CRHBYTES]);
+        shake256(seed, seedbuf.as_mut());
+        let (rho, rest) = seedbuf.split_at(SEEDBYTES);
+        let (rhoprime, key) = rest.split_at(CRHBYTES);
+
+        let mut mat = [ZERO_L; K];
+        let (mut s1, mut s2, mut t1, mut t0) = (ZERO_L, ZERO_K, ZERO_K, ZERO_K);
+        let mut pk = vec![0u8; PUBLICKEYBYTES];
+        let mut sk = Zeroizing::new(vec![0u8; SECRETKEYBYTES]);
+        let mut tr = Zeroizing::new([0u8; TRBYTES]);
+        // SAFETY: `mat` ha K righe da PolyVecL come `polyvecl mat[K]` in sign.c, `rho` ha SEEDBYTES byte e
+        // `rhoprime` CRHBYTES; le strutture hanno il layout di PQClean (verificato sopra a compilazione).
+        unsafe {
+            PQCLEAN_DILITHIUM5_CLEAN_polyvec_matrix_expand(mat.as_mut_ptr(), rho.as_ptr());
+            PQCLEAN_DILITHIUM5_CLEAN_polyvecl_uniform_eta(&mut s1, rhoprime.as_ptr(), 0);
+            PQCLEAN_DILITHIUM5_CLEAN_polyveck_uniform_eta(&mut s2, rhoprime.as_ptr(), L as u16);
+        }
+
+        let mut s1hat = s1;
+        // SAFETY: puntatori a variabili locali distinte e inizializzate, delle dimensioni che le funzioni si aspettano
+        unsafe {
+            PQCLEAN_DILITHIUM5_CLEAN_polyvecl_ntt(&mut s1hat);
+            PQCLEAN_DILITHIUM5_CLEAN_polyvec_matrix_pointwise_montgomery(&mut t1, mat.as_ptr(), &s1hat);
+            PQCLEAN_DILITHIUM5_CLEAN_polyveck_reduce(&mut t1);
+            PQCLEAN_DILITHIUM5_CLEAN_polyveck_invntt_tomont(&mut t1);
+        }
+
+        let t1_ptr: *mut PolyVecK = &mut t1;
+        // SAFETY: come in sign.c t1 è sia ingresso sia uscita: le funzioni lavorano coefficiente per coefficiente,
+        // quindi l'aliasing è ammesso; un unico puntatore grezzo evita riferimenti &mut sovrapposti
+        unsafe {
+            PQCLEAN_DILITHIUM5_CLEAN_polyveck_add(t1_ptr, t1_ptr, &s2);
+            PQCLEAN_DILITHIUM5_CLEAN_polyveck_caddq(t1_ptr);
+            PQCLEAN_DILITHIUM5_CLEAN_polyveck_power2round(t1_ptr, &mut t0, t1_ptr);
+        }
+
+        // SAFETY: `pk` ha PUBLICKEYBYTES byte, `sk` SECRETKEYBYTES e `tr` TRBYTES (le dimensioni di pqcrypto, vedi sopra)
+        unsafe {
+            PQCLEAN_DILITHIUM5_CLEAN_pack_pk(pk.as_mut_ptr(), rho.as_ptr(), &t1);
+        }
+        shake256(&pk, tr.as_mut());
+        // SAFETY: come sopra; `key` ha SEEDBYTES byte, t0/s1/s2 sono inizializzati
+        unsafe {
+            PQCLEAN_DILITHIUM5_CLEAN_pack_sk(sk.as_mut_ptr(), rho.as_ptr(), tr.as_ptr(), key.as_ptr(), &t0, &s1, &s2);
+        }
+
+        for poly in s1.vec.iter_mut().chain(s1hat.vec.iter_mut()) {
+            poly.coeffs.zeroize();
+        }
+        for poly in s2.vec.iter_mut().chain(t0.vec.iter_mut()) {
+            poly.coeffs.zeroize();
+        }
+        (pk, sk)
+    }
+}
+
+// hex(id ‖ byte): formato di chiavi pubbliche e firme
+pub fn encode(algorithm: Algorithm, bytes: &[u8]) -> String {
+    let mut tagged = Vec::with_capacity(bytes.len() + 1);
+    tagged.push(algorithm.id());
+    tagged.extend_from_slice(bytes);
+    hex::encode(tagged)
+}
+
+pub fn decode(tagged_hex: &str) -> Option<(Algorithm, Vec<u8>)> {
+    let mut bytes = hex::decode(tagged_hex).ok()?;
+    if bytes.is_empty() {
+        return None;
+    }
+    let algorithm = Algorithm::from_id(bytes.remove(0))?;
+    Some((algorithm, bytes))
+}
+
+// Algoritmo di una chiave pubblica codificata (None se il formato non è valido)
+pub fn algorithm_of(public_key_hex: &str) -> Option<Algorithm> {
+    decode(public_key_hex)
+        .filter(|(algorithm, bytes)| algorithm.is_valid_public_key(bytes))
+        .map(|(algorithm, _)| algorithm)
+}
+
+// La firma deve usare lo stesso algoritmo della chiave: niente "downgrade" a schemi più deboli
+pub fn verify(message: &str, signature_hex: &str, public_key_hex: &str) -> bool {
+    match (decode(public_key_hex), decode(signature_hex)) {
+        (Some((key_algorithm, public_key)), Some((sig_algorithm, signature))) if key_algorithm == sig_algorithm => {
+            key_algorithm.verify(message.as_bytes(), &signature, &public_key)
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sha3::{Digest, Sha3_256};
+
+    // Vettore noto: seed fisso -> chiavi fisse. Ricavato anche con crypto_sign_keypair di PQClean
+    // (randombytes = seed); se cambia, le frasi di recupero non ritrovano più i loro conti
+    #[test]
+    fn seeded_keypair_known_answer() {
+        let (public_key, secret_key) = dilithium5_seeded::keypair(&[7u8; 32]);
+        assert_eq!(hex::encode(Sha3_256::digest(&public_key)), "d691fb93257925854b32740ab9cd66e0affbfbdc651ce621a17cd6a7c80e8365");
+        assert_eq!(hex::encode(Sha3_256::digest(secret_key.as_slice())), "2a7979bb3b6f4f49b503ad4fb894953a53579953d389b7520d2cfcdf187bb2b8");
+        let signature = Algorithm::Dilithium5.sign(b"ADAMAS", &secret_key).unwrap();
+        assert!(Algorithm::Dilithium5.verify(b"ADAMAS", &signature, &public_key));
+    }
+
+    #[test]
+    fn algorithm_id_must_match_the_key() {
+        for algorithm in [Algorithm::Dilithium2, Algorithm::Falcon512] {
+            let (public_key, secret_key) = algorithm.keypair();
+            let signature = algorithm.sign(b"ADAMAS", &secret_key).unwrap();
+            let (public_hex, signature_hex) = (encode(algorithm, &public_key), encode(algorithm, &signature));
+            assert!(verify("ADAMAS", &signature_hex, &public_hex));
+            assert!(!verify("ADAMAS!", &signature_hex, &public_hex));
+            assert_eq!(algorithm_of(&public_hex), Some(algorithm));
+
+            // Stessi byte con l'ID di un altro algoritmo (sulla firma, sulla chiave o su entrambe)
+            let other = if algorithm == Algorithm::Dilithium2 { Algorithm::Dilithium3 } else { Algorithm::Falcon1024 };
+            assert!(!verify("ADAMAS", &encode(other, &signature), &public_hex));
+            assert!(!verify("ADAMAS", &signature_hex, &encode(other, &public_key)));
+            assert!(!verify("ADAMAS", &encode(other, &signature), &encode(other, &public_key)));
+        }
+        assert_eq!(decode("ff00"), None);
+        assert_eq!(decode(""), None);
+    }
+
+    #[test]
+    fn hybrid_needs_both_halves() {
+        let hybrid = Algorithm::HybridEd25519Dilithium5;
+        let (public_key, secret_key) = hybrid.keypair();
+        let (_, other_secret) = hybrid.keypair();
+        let signature = hybrid.sign(b"ADAMAS", &secret_key).unwrap();
+        let foreign = hybrid.sign(b"ADAMAS", &other_secret).unwrap();
+        assert!(hybrid.verify(b"ADAMAS", &signature, &public_key));
+
+        // Metà Ed25519 giusta e metà Dilithium di un'altra chiave, e viceversa
+        let split = 64;
+        let classic_only = [&signature[..split], &foreign[split..]].concat();
+        let post_quantum_only = [&foreign[..split], &signature[split..]].concat();
+        assert!(!hybrid.verify(b"ADAMAS", &classic_only, &public_key));
+        assert!(!hybrid.verify(b"ADAMAS", &post_quantum_only, &public_key));
+
+        // Una firma Dilithium-5 da sola non vale per la chiave ibrida
+        let (_, pq_secret) = Algorithm::Dilithium5.keypair();
+        let pq_signature = Algorithm::Dilithium5.sign(b"ADAMAS", &pq_secret).unwrap();
+        assert!(!hybrid.verify(b"ADAMAS", &pq_signature, &public_key));
+        assert!(!verify("ADAMAS", &encode(Algorithm::Dilithium5, &signature[split..]), &encode(hybrid, &public_key)));
+    }
+}
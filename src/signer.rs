@@ -0,0 +1,437 @@
+// Firmatari: chi custodisce le chiavi e firma per conto del nodo o del wallet.
+// `Wallet` firma nello stesso processo; `RemoteSigner` delega a un processo separato
+// (adamas-signer) su un socket Unix locale, così le chiavi del validatore non stanno nella memoria del nodo.
+// Dove i socket Unix non ci sono (Windows) si usa un indirizzo TCP di loopback, es. 127.0.0.1:7420.
+// Protocollo: una richiesta JSON per connessione (con il token del chiamante), una riga di risposta.
+use crate::address;
+use crate::wallet::Wallet;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::OsRng;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+#[cfg(unix)]
+use std::os::unix::fs::{FileTypeExt, OpenOptionsExt, PermissionsExt};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::time::Duration;
+
+// Prefisso dei messaggi di blocco: una firma "generica" non può mai valere come firma di un blocco
+pub const BLOCK_DOMAIN: &str = "ADAMAS-BLOCK|";
+
+// Prefisso dei messaggi di testo (`adamas-wallet sign`): un testo scelto da altri non può valere come transazione
+pub const MESSAGE_DOMAIN: &str = "ADAMAS-MESSAGE|";
+
+// Una firma SPHINCS+ può richiedere qualche secondo
+const SIGNER_TIMEOUT: Duration = Duration::from_secs(30);
+
+pub trait Signer {
+    // Chiave pubblica codificata (vedi signature.rs)
+    fn public_key(&self) -> &str;
+
+    // Firma transazioni e messaggi; rifiuta i messaggi di blocco
+    fn sign_message(&self, message: &str) -> Result<String, Box<dyn Error>>;
+
+    // Firma l'header di un blocco. Un firmatario remoto rifiuta di firmare due blocchi diversi alla stessa altezza.
+    fn sign_block(&self, index: u64, hash: &str) -> Result<String, Box<dyn Error>>;
+
+    fn address(&self) -> String {
+        address::from_public_key(self.public_key())
+    }
+}
+
+// Dove ascolta adamas-signer: percorso di un socket Unix oppure indirizzo TCP di loopback
+enum Endpoint {
+    #[cfg(unix)]
+    Unix(String),
+    Tcp(SocketAddr),
+}
+
+impl Endpoint {
+    // Un indirizzo TCP deve essere locale: il firmatario non si espone mai sulla rete
+    fn parse(location: &str) -> Result<Self, Box<dyn Error>> {
+        if let Ok(addr) = location.parse::<SocketAddr>() {
+            if !addr.ip().is_loopback() {
+                return Err(format!("signer address {} is not a loopback address", location).into());
+            }
+            return Ok(Endpoint::Tcp(addr));
+        }
+        #[cfg(unix)]
+        return Ok(Endpoint::Unix(location.to_string()));
+        #[cfg(not(unix))]
+        Err(format!("{} is not a loopback address (es. 127.0.0.1:7420): Unix sockets are not available on this system", location).into())
+    }
+}
+
+// Vero se `location` indica un adamas-signer (indirizzo TCP o socket Unix esistente) invece di un keystore
+pub fn is_endpoint(location: &str) -> bool {
+    if location.parse::<SocketAddr>().is_ok() {
+        return true;
+    }
+    #[cfg(unix)]
+    return fs::metadata(location).is_ok_and(|metadata| metadata.file_type().is_socket());
+    #[cfg(not(unix))]
+    false
+}
+
+// File con il token che adamas-signer chiede a ogni chiamata: accanto al socket Unix, oppure (TCP di loopback)
+// nella cartella temporanea dell'utente. Lo legge solo chi gira con lo stesso utente del firmatario.
+pub fn token_path(location: &str) -> Result<PathBuf, Box<dyn Error>> {
+    Ok(match Endpoint::parse(location)? {
+        #[cfg(unix)]
+        Endpoint::Unix(path) => PathBuf::from(format!("{}.token", path)),
+        Endpoint::Tcp(addr) => env::temp_dir().join(format!("adamas-signer-{}.token", addr.port())),
+    })
+}
+
+// Confronto a tempo costante: il tempo di risposta non rivela quanti caratteri del token sono giusti
+fn same_token(expected: &str, given: &str) -> bool {
+    expected.len() == given.len() && expected.bytes().zip(given.bytes()).fold(0u8, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+// Una connessione verso o da adamas-signer, Unix o TCP
+trait Connection: Read + Write {
+    fn set_timeouts(&self, timeout: Duration) -> io::Result<()>;
+}
+
+impl Connection for TcpStream {
+    fn set_timeouts(&self, timeout: Duration) -> io::Result<()> {
+        self.set_read_timeout(Some(timeout))?;
+        self.set_write_timeout(Some(timeout))
+    }
+}
+
+#[cfg(unix)]
+impl Connection for UnixStream {
+    fn set_timeouts(&self, timeout: Duration) -> io::Result<()> {
+        self.set_read_timeout(Some(timeout))?;
+        self.set_write_timeout(Some(timeout))
+    }
+}
+
+// Messaggio firmato dal produttore del blocco
+pub fn block_message(index: u64, hash: &str) -> String {
+    format!("{}{}|{}", BLOCK_DOMAIN, index, hash)
+}
+
+// Messaggio di testo firmato dall'utente
+pub fn text_message(message: &str) -> String {
+    format!("{}{}", MESSAGE_DOMAIN, message)
+}
+
+fn check_not_block(message: &str) -> Result<(), Box<dyn Error>> {
+    if message.starts_with(BLOCK_DOMAIN) {
+        return Err("block headers must be signed with sign_block".into());
+    }
+    Ok(())
+}
+
+impl Signer for Wallet {
+    fn public_key(&self) -> &str {
+        &self.public_key
+    }
+
+    fn sign_message(&self, message: &str) -> Result<String, Box<dyn Error>> {
+        check_not_block(message)?;
+        Ok(self.sign(message))
+    }
+
+    // Nello stesso processo non c'è stato persistente contro la doppia firma: per i validatori usare adamas-signer
+    fn sign_block(&self, index: u64, hash: &str) -> Result<String, Box<dyn Error>> {
+        Ok(self.sign(&block_message(index, hash)))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum SignerRequest {
+    PublicKey,
+    SignMessage { message: String },
+    SignBlock { index: u64, hash: String },
+}
+
+// Richiesta con il token letto da `token_path`: senza, adamas-signer non risponde
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SignerCall {
+    pub token: String,
+    pub request: SignerRequest,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub enum SignerResponse {
+    PublicKey(String),
+    Signature(String),
+    Refused(String),
+}
+
+// Lato client: il nodo o il wallet parlano con adamas-signer
+#[derive(Debug, Clone)]
+pub struct RemoteSigner {
+    socket_path: String,
+    public_key: String,
+    token: String,
+}
+
+impl RemoteSigner {
+    // Chiede subito la chiave pubblica: un firmatario irraggiungibile si scopre all'avvio, non al primo blocco
+    pub fn connect(socket_path: &str) -> Result<Self, Box<dyn Error>> {
+        let path = token_path(socket_path)?;
+        let token = fs::read_to_string(&path)
+            .map_err(|e| format!("cannot read the signer token {}: {} (is adamas-signer running as this user?)", path.display(), e))?;
+        let mut signer = RemoteSigner { socket_path: socket_path.to_string(), public_key: String::new(), token: token.trim().to_string() };
+        match signer.request(&SignerRequest::PublicKey)? {
+            SignerResponse::PublicKey(public_key) => signer.public_key = public_key,
+            other => return Err(format!("unexpected signer response: {:?}", other).into()),
+        }
+        Ok(signer)
+    }
+
+    fn request(&self, request: &SignerRequest) -> Result<SignerResponse, Box<dyn Error>> {
+        let unreachable = |e: io::Error| format!("signer unreachable at {}: {}", self.socket_path, e);
+        match Endpoint::parse(&self.socket_path)? {
+            #[cfg(unix)]
+            Endpoint::Unix(path) => self.exchange(UnixStream::connect(path).map_err(unreachable)?, request),
+            Endpoint::Tcp(addr) => self.exchange(TcpStream::connect_timeout(&addr, SIGNER_TIMEOUT).map_err(unreachable)?, request),
+        }
+    }
+
+    fn exchange(&self, mut stream: impl Connection, request: &SignerRequest) -> Result<SignerResponse, Box<dyn Error>> {
+        stream.set_timeouts(SIGNER_TIMEOUT)?;
+        let mut line = serde_json::to_string(&SignerCall { token: self.token.clone(), request: request.clone() })?;
+        line.push('\n');
+        stream.write_all(line.as_bytes())?;
+
+        let mut response = String::new();
+        BufReader::new(stream).read_line(&mut response)?;
+        Ok(serde_json::from_str(&response)?)
+    }
+
+    fn signature(&self, request: &SignerRequest) -> Result<String, Box<dyn Error>> {
+        match self.request(request)? {
+            SignerResponse::Signature(signature) => Ok(signature),
+            SignerResponse::Refused(reason) => Err(format!("signer refused: {}", reason).into()),
+            other => Err(format!("unexpected signer response: {:?}", other).into()),
+        }
+    }
+}
+
+impl Signer for RemoteSigner {
+    fn public_key(&self) -> &str {
+        &self.public_key
+    }
+
+    fn sign_message(&self, message: &str) -> Result<String, Box<dyn Error>> {
+        self.signature(&SignerRequest::SignMessage { message: message.to_string() })
+    }
+
+    fn sign_block(&self, index: u64, hash: &str) -> Result<String, Box<dyn Error>> {
+        self.signature(&SignerRequest::SignBlock { index, hash: hash.to_string() })
+    }
+}
+
+// Ultimo blocco firmato, salvato su disco PRIMA di restituire la firma:
+// anche dopo un riavvio del firmatario non si firma un secondo blocco alla stessa altezza
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct SigningState {
+    pub last_index: u64,
+    pub last_hash: String, // Vuoto finché non si firma il primo blocco
+    pub last_signature: String,
+}
+
+impl SigningState {
+    pub fn load(path: &str) -> Result<Self, Box<dyn Error>> {
+        match fs::read_to_string(path) {
+            Ok(content) => Ok(serde_json::from_str(&content)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(SigningState::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    // Scrittura atomica: un file a metà non deve azzerare la protezione
+    pub fn save(&self, path: &str) -> Result<(), Box<dyn Error>> {
+        let tmp = format!("{}.tmp", path);
+        fs::write(&tmp, serde_json::to_string_pretty(self)?)?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+}
+
+// Lato server: il processo adamas-signer, unico a conoscere la chiave segreta
+pub struct SignerService {
+    wallet: Wallet,
+    state_path: String,
+    state: SigningState,
+    token: String, // Nuovo a ogni avvio
+}
+
+impl SignerService {
+    pub fn new(wallet: Wallet, state_path: &str) -> Result<Self, Box<dyn Error>> {
+        let state = SigningState::load(state_path)?;
+        let mut token = [0u8; 32];
+        OsRng.fill_bytes(&mut token);
+        Ok(SignerService { wallet, state_path: state_path.to_string(), state, token: hex::encode(token) })
+    }
+
+    pub fn address(&self) -> String {
+        self.wallet.address()
+    }
+
+    pub fn handle(&mut self, call: SignerCall) -> SignerResponse {
+        if !same_token(&self.token, &call.token) {
+            println!("   [SIGNER] ⛔ Richiesta senza un token valido rifiutata");
+            return SignerResponse::Refused("invalid caller token".to_string());
+        }
+        let result = match call.request {
+            SignerRequest::PublicKey => return SignerResponse::PublicKey(self.wallet.public_key.clone()),
+            SignerRequest::SignMessage { message } => self.wallet.sign_message(&message),
+            SignerRequest::SignBlock { index, hash } => self.sign_block(index, &hash),
+        };
+        match result {
+            Ok(signature) => SignerResponse::Signature(signature),
+            Err(e) => SignerResponse::Refused(e.to_string()),
+        }
+    }
+
+    fn sign_block(&mut self, index: u64, hash: &str) -> Result<String, Box<dyn Error>> {
+        if !self.state.last_hash.is_empty() {
+            if index == self.state.last_index && hash == self.state.last_hash {
+                // Stesso blocco richiesto di nuovo (es. il nodo non ha ricevuto la risposta): stessa firma
+                return Ok(self.state.last_signature.clone());
+            }
+            if index <= self.state.last_index {
+                println!("   [SIGNER] ⛔ Rifiutata doppia firma: blocco #{} (ultimo firmato #{})", index, self.state.last_index);
+                return Err(format!("double sign: block #{} is not above the last signed block #{}", index, self.state.last_index).into());
+            }
+        }
+        let signature = self.wallet.sign_block(index, hash)?;
+        let state = SigningState { last_index: index, last_hash: hash.to_string(), last_signature: signature.clone() };
+        state.save(&self.state_path)?;
+        self.state = state;
+        println!("   [SIGNER] 🔏 Blocco #{} firmato", index);
+        Ok(signature)
+    }
+
+    // Serve le richieste una alla volta: lo stato anti doppia firma non ha accessi concorrenti.
+    // `location` è il percorso di un socket Unix oppure un indirizzo TCP di loopback.
+    pub fn serve(self, location: &str) -> Result<(), Box<dyn Error>> {
+        self.write_token(location)?;
+        match Endpoint::parse(location)? {
+            #[cfg(unix)]
+            Endpoint::Unix(path) => self.serve_unix(&path),
+            // Sul loopback TCP qualsiasi processo locale può chiedere firme: dove si può, meglio il socket Unix (0600)
+            Endpoint::Tcp(addr) => self.serve_connections(TcpListener::bind(addr)?.incoming()),
+        }
+    }
+
+    // Il file si ricrea a ogni avvio; se appartiene a un altro utente la creazione fallisce
+    fn write_token(&self, location: &str) -> Result<(), Box<dyn Error>> {
+        let path = token_path(location)?;
+        if let Err(e) = fs::remove_file(&path) {
+            if e.kind() != io::ErrorKind::NotFound {
+                return Err(format!("cannot replace the signer token {}: {}", path.display(), e).into());
+            }
+        }
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        options.mode(0o600);
+        options.open(&path)?.write_all(self.token.as_bytes())?;
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    fn serve_unix(self, socket_path: &str) -> Result<(), Box<dyn Error>> {
+        // Un socket rimasto da un'esecuzione precedente si può rimuovere, un file qualsiasi no
+        if let Ok(metadata) = fs::symlink_metadata(socket_path) {
+            if !metadata.file_type().is_socket() {
+                return Err(format!("{} exists and is not a socket", socket_path).into());
+            }
+            fs::remove_file(socket_path)?;
+        }
+        let listener = UnixListener::bind(socket_path)?;
+        // Solo l'utente che esegue il firmatario può chiedere firme
+        fs::set_permissions(socket_path, fs::Permissions::from_mode(0o600))?;
+        self.serve_connections(listener.incoming())
+    }
+
+    fn serve_connections<C: Connection>(mut self, incoming: impl Iterator<Item = io::Result<C>>) -> Result<(), Box<dyn Error>> {
+        for stream in incoming {
+            match stream {
+                Ok(stream) => {
+                    if let Err(e) = self.handle_connection(stream) {
+                        println!("   [SIGNER] ❌ Richiesta non valida: {}", e);
+                    }
+                }
+                Err(e) => println!("   [SIGNER] ❌ Connection Error: {}", e),
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_connection(&mut self, mut stream: impl Connection) -> Result<(), Box<dyn Error>> {
+        stream.set_timeouts(SIGNER_TIMEOUT)?;
+        let mut line = String::new();
+        BufReader::new(&mut stream).read_line(&mut line)?;
+        let response = self.handle(serde_json::from_str(&line)?);
+        let mut reply = serde_json::to_string(&response)?;
+        reply.push('\n');
+        stream.write_all(reply.as_bytes())?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signature;
+
+    fn state_path(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("adamas-signer-{}-{}.json", name, std::process::id()));
+        let _ = fs::remove_file(&path);
+        path.to_string_lossy().into_owned()
+    }
+
+    fn sign(service: &mut SignerService, index: u64, hash: &str) -> Result<String, String> {
+        let call = SignerCall { token: service.token.clone(), request: SignerRequest::SignBlock { index, hash: hash.to_string() } };
+        match service.handle(call) {
+            SignerResponse::Signature(signature) => Ok(signature),
+            SignerResponse::Refused(reason) => Err(reason),
+            other => panic!("unexpected response {:?}", other),
+        }
+    }
+
+    #[test]
+    fn refuses_double_sign_but_repeats_the_same_block() {
+        let path = state_path("double-sign");
+        let wallet = Wallet::new();
+        let public_key = wallet.public_key.clone();
+        let mut service = SignerService::new(wallet, &path).unwrap();
+
+        let first = sign(&mut service, 5, "aa").unwrap();
+        assert!(signature::verify(&block_message(5, "aa"), &first, &public_key));
+        // Il nodo non ha ricevuto la risposta o l'aggiunta è fallita: stesso blocco, stessa firma
+        assert_eq!(sign(&mut service, 5, "aa").unwrap(), first);
+        assert!(sign(&mut service, 5, "bb").is_err());
+        assert!(sign(&mut service, 4, "cc").is_err());
+        sign(&mut service, 6, "dd").unwrap();
+
+        // La protezione sopravvive al riavvio del firmatario
+        let mut restarted = SignerService::new(Wallet::new(), &path).unwrap();
+        assert!(sign(&mut restarted, 6, "ee").is_err());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn refuses_callers_without_the_token() {
+        let path = state_path("token");
+        let mut service = SignerService::new(Wallet::new(), &path).unwrap();
+        let message = SignerRequest::SignMessage { message: text_message("hello") };
+        let forged = SignerCall { token: "00".repeat(32), request: message.clone() };
+        assert!(matches!(service.handle(forged), SignerResponse::Refused(_)));
+        let call = SignerCall { token: service.token.clone(), request: message };
+        assert!(matches!(service.handle(call), SignerResponse::Signature(_)));
+    }
+}
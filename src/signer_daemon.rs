@@ -0,0 +1,63 @@
+// ADAMAS SIGNER: processo separato che custodisce la chiave del validatore.
+// Il nodo (signer_socket in node_config.json) e il wallet gli chiedono le firme su un socket Unix locale
+// (su Windows su un indirizzo TCP di loopback).
+use adamas_core::signer::{self, SignerService};
+use adamas_core::wallet::Wallet;
+use std::env;
+use std::error::Error;
+use std::io::{self, BufRead, Write};
+
+// Stessa variabile del wallet: evita la richiesta interattiva della passphrase
+const PASSPHRASE_ENV: &str = "ADAMAS_PASSPHRASE";
+
+#[cfg(unix)]
+const DEFAULT_SOCKET: &str = "adamas-signer.sock";
+#[cfg(not(unix))]
+const DEFAULT_SOCKET: &str = "127.0.0.1:7420";
+
+const USAGE: &str = "ADAMAS SIGNER
+
+USO: adamas-signer <keystore> [socket] [file di stato]
+
+  socket          percorso di un socket Unix oppure indirizzo di loopback (es. 127.0.0.1:7420),
+                  default adamas-signer.sock (127.0.0.1:7420 dove i socket Unix non ci sono)
+  (all'avvio scrive un token in <socket>.token, o nella cartella temporanea per il TCP: chi chiede firme deve leggerlo)
+  file di stato   ultimo blocco firmato (protezione dalla doppia firma), default <keystore>.signer-state.json";
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("❌ {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn run() -> Result<(), Box<dyn Error>> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let keystore = match args.first() {
+        Some(keystore) => keystore.as_str(),
+        None => {
+            println!("{}", USAGE);
+            return Ok(());
+        }
+    };
+    let socket = args.get(1).map(|s| s.as_str()).unwrap_or(DEFAULT_SOCKET);
+    let state_path = args.get(2).cloned().unwrap_or_else(|| format!("{}.signer-state.json", keystore));
+
+    let passphrase = match env::var(PASSPHRASE_ENV) {
+        Ok(passphrase) => passphrase,
+        Err(_) => {
+            eprint!("Passphrase: ");
+            io::stderr().flush()?;
+            let mut line = String::new();
+            io::stdin().lock().read_line(&mut line)?;
+            line.trim().to_string()
+        }
+    };
+    let service = SignerService::new(Wallet::load_keystore(keystore, &passphrase)?, &state_path)?;
+
+    println!("🔏 ADAMAS SIGNER STARTED");
+    println!("🔑 ADDRESS: {}", service.address());
+    println!("📡 SOCKET: {} (stato: {})", socket, state_path);
+    println!("🎟️ TOKEN: {} (solo chi può leggerlo ottiene firme)", signer::token_path(socket)?.display());
+    service.serve(socket)
+}
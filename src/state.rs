@@ -0,0 +1,704 @@
+use crate::address;
+use crate::block::Block;
+use crate::database::BlockchainDB;
+use crate::merkle::{NodeUpdates, SparseMerkleTree, StateProof};
+use crate::notary::DocumentAnchor;
+use crate::supply::{self, MAX_SUPPLY};
+use crate::transaction::{Transaction, TransactionKind};
+use crate::verifier;
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Sha3_256};
+use sled::transaction::ConflictableTransactionError;
+use sled::{Batch, Transactional, Tree};
+use std::collections::HashMap;
+use std::error::Error;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum Direction {
+    In,
+    Out,
+    Fee,  // Commissioni incassate come produttore del blocco
+    Mint, // ADM nuovi: ricompensa di blocco o allocazione di genesi
+    KeyRotation, // Chiave autorizzata sostituita: la controparte è l'impronta della nuova chiave
+    Certification, // Record di certificazione firmato (il contenuto è nella transazione del blocco)
+    ContractDeploy, // Contratto pubblicato: la controparte è l'indirizzo del contratto
+    DocumentAnchor, // Documento notarizzato: la controparte è l'impronta del documento
+}
+
+// Una riga dello storico di un conto
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HistoryEntry {
+    pub block_index: u64,
+    pub direction: Direction,
+    pub counterparty: String,
+    pub amount: u64,
+    pub fee: u64,
+}
+
+// Il "Libro Mastro": saldi, nonce e storico di ogni indirizzo, persistiti su disco
+pub struct AccountState {
+    balances: Tree,
+    nonces: Tree,
+    public_keys: Tree, // Chiave registrata alla prima spesa di ogni indirizzo (o dall'ultima rotazione)
+    history: Tree,
+    documents: Tree, // Impronta del documento -> primo ancoraggio
+    meta: Tree, // Valori globali (es. offerta in circolazione)
+    tree: SparseMerkleTree, // Impegno crittografico sullo stato: la radice va nell'header del blocco
+}
+
+// Tutte le scritture di un blocco, calcolate e validate prima di toccare il disco
+struct PreparedBlock {
+    balances: Batch,
+    nonces: Batch,
+    public_keys: Batch,
+    history: Batch,
+    documents: Batch,
+    circulating: u64,
+    state_root: String,
+    node_updates: NodeUpdates,
+}
+
+const CIRCULATING_KEY: &str = "circulating";
+// Hash del blocco di genesi applicato: le sue allocazioni si coniano una volta sola
+const GENESIS_KEY: &str = "genesis";
+
+// Saldi modificati ma non ancora scritti (serve per validare un blocco intero prima di salvarlo)
+struct Overlay<'a> {
+    state: &'a AccountState,
+    balances: HashMap<String, u64>,
+    nonces: HashMap<String, u64>,
+    public_keys: HashMap<String, String>,
+    documents: HashMap<String, DocumentAnchor>,
+}
+
+impl<'a> Overlay<'a> {
+    fn new(state: &'a AccountState) -> Self {
+        Overlay { state, balances: HashMap::new(), nonces: HashMap::new(), public_keys: HashMap::new(), documents: HashMap::new() }
+    }
+
+    fn document(&self, hash: &str) -> Result<Option<DocumentAnchor>, Box<dyn Error>> {
+        match self.documents.get(hash) {
+            Some(anchor) => Ok(Some(anchor.clone())),
+            None => self.state.document(hash),
+        }
+    }
+
+    fn public_key(&self, address: &str) -> Result<Option<String>, Box<dyn Error>> {
+        match self.public_keys.get(address) {
+            Some(key) => Ok(Some(key.clone())),
+            None => self.state.public_key(address),
+        }
+    }
+
+    fn nonce(&self, address: &str) -> Result<u64, Box<dyn Error>> {
+        match self.nonces.get(address) {
+            Some(nonce) => Ok(*nonce),
+            None => self.state.nonce(address),
+        }
+    }
+
+    fn balance(&self, address: &str) -> Result<u64, Box<dyn Error>> {
+        match self.balances.get(address) {
+            Some(balance) => Ok(*balance),
+            None => self.state.balance(address),
+        }
+    }
+
+    fn credit(&mut self, address: &str, amount: u64) -> Result<(), Box<dyn Error>> {
+        if amount == 0 {
+            return Ok(());
+        }
+        let balance = self.balance(address)?;
+        let new_balance = balance.checked_add(amount).ok_or("balance overflow")?;
+        self.balances.insert(address.to_string(), new_balance);
+        Ok(())
+    }
+
+    // Applica un trasferimento se la firma è valida, il nonce è quello atteso, il blocco cade nella
+    // finestra di validità e il mittente può pagare importo + commissione; la commissione va al produttore del blocco
+    fn apply(&mut self, tx: &Transaction, block: &Block) -> Result<(), Box<dyn Error>> {
+        let validator = block.validator.as_str();
+        let registered_key = self.public_key(&tx.sender)?;
+        if !verifier::verify(tx, registered_key.as_deref()) {
+            return Err("invalid signature".into());
+        }
+        if !address::is_valid(&tx.receiver) {
+            return Err(format!("invalid receiver address: {}", tx.receiver).into());
+        }
+        tx.validate()?;
+        tx.check_window(block.index, (block.timestamp / 1000) as u64)?;
+        let expected_nonce = self.nonce(&tx.sender)?;
+        if tx.nonce != expected_nonce {
+            return Err(format!("wrong nonce: expected {}, got {}", expected_nonce, tx.nonce).into());
+        }
+        // Un'impronta già ancorata (anche nello stesso blocco) non si ancora di nuovo
+        if let TransactionKind::AnchorDocument(document) = &tx.kind {
+            if let Some(anchor) = self.document(&document.hash)? {
+                return Err(format!("document {} is already anchored in block #{}", document.hash, anchor.block_index).into());
+            }
+        }
+        let total = tx.amount.checked_add(tx.fee).ok_or("amount overflow")?;
+        let sender_balance = self.balance(&tx.sender)?;
+        if sender_balance < total {
+            return Err(format!("insufficient funds: balance {}, amount {} + fee {}", sender_balance, tx.amount, tx.fee).into());
+        }
+        self.balances.insert(tx.sender.clone(), sender_balance - total);
+        self.nonces.insert(tx.sender.clone(), expected_nonce + 1);
+        if let TransactionKind::KeyRotation { new_key } = &tx.kind {
+            // Da qui in poi firma solo la nuova chiave: quella vecchia non autorizza più nulla
+            self.public_keys.insert(tx.sender.clone(), new_key.clone());
+        } else if registered_key.is_none() {
+            self.public_keys.insert(tx.sender.clone(), tx.public_key.clone());
+        }
+        if let TransactionKind::AnchorDocument(document) = &tx.kind {
+            let anchor = DocumentAnchor {
+                block_index: block.index,
+                block_timestamp: block.timestamp,
+                tx_id: tx.id(),
+                owner: tx.sender.clone(),
+            };
+            self.documents.insert(document.hash.clone(), anchor);
+        }
+
+        self.credit(&tx.receiver, tx.amount)?;
+        self.credit(validator, tx.fee)?;
+        Ok(())
+    }
+}
+
+impl AccountState {
+    pub fn open(db: &BlockchainDB) -> Result<Self, Box<dyn Error>> {
+        Ok(AccountState {
+            balances: db.open_tree("balances")?,
+            nonces: db.open_tree("nonces")?,
+            public_keys: db.open_tree("public_keys")?,
+            history: db.open_tree("history")?,
+            documents: db.open_tree("documents")?,
+            meta: db.open_tree("meta")?,
+            tree: SparseMerkleTree::new(db.open_tree("state_tree")?),
+        })
+    }
+
+    // Valore della foglia di un conto: saldo, nonce e hash della chiave registrata
+    // (vuoto se il conto non esiste)
+    fn account_leaf(balance: u64, nonce: u64, public_key: Option<&str>) -> Vec<u8> {
+        if balance == 0 && nonce == 0 && public_key.is_none() {
+            return Vec::new();
+        }
+        let mut value = balance.to_be_bytes().to_vec();
+        value.extend_from_slice(&nonce.to_be_bytes());
+        if let Some(key) = public_key {
+            value.extend_from_slice(&Sha3_256::digest(key.as_bytes()));
+        }
+        value
+    }
+
+    // Prova che lo stato di `address` è incluso nella radice di stato attuale
+    pub fn prove_account(&self, address: &str) -> Result<StateProof, Box<dyn Error>> {
+        let public_key = self.public_key(address)?;
+        let value = Self::account_leaf(self.balance(address)?, self.nonce(address)?, public_key.as_deref());
+        self.tree.prove(&account_key(address), &value)
+    }
+
+    // ADM già coniati (allocazioni di genesi + ricompense), in unità
+    pub fn circulating_supply(&self) -> Result<u64, Box<dyn Error>> {
+        read_u64(&self.meta, CIRCULATING_KEY)
+    }
+
+    // Ricompensa massima che il prossimo blocco può coniare
+    pub fn next_block_reward(&self, height: u64) -> Result<u64, Box<dyn Error>> {
+        Ok(supply::block_reward(height, self.circulating_supply()?))
+    }
+
+    pub fn balance(&self, address: &str) -> Result<u64, Box<dyn Error>> {
+        read_u64(&self.balances, address)
+    }
+
+    // Il prossimo nonce che il mittente deve usare (0 per un conto nuovo)
+    pub fn nonce(&self, address: &str) -> Result<u64, Box<dyn Error>> {
+        read_u64(&self.nonces, address)
+    }
+
+    pub fn public_key(&self, address: &str) -> Result<Option<String>, Box<dyn Error>> {
+        match self.public_keys.get(address)? {
+            Some(bytes) => Ok(Some(String::from_utf8(bytes.to_vec())?)),
+            None => Ok(None),
+        }
+    }
+
+    // Primo ancoraggio di un documento (None se l'impronta non è mai stata ancorata)
+    pub fn document(&self, hash: &str) -> Result<Option<DocumentAnchor>, Box<dyn Error>> {
+        match self.documents.get(hash)? {
+            Some(data) => Ok(Some(bincode::deserialize(&data)?)),
+            None => Ok(None),
+        }
+    }
+
+    // Prova che il documento è ancorato (o, con valore vuoto, che non lo è) nello stato attuale
+    pub fn prove_document(&self, hash: &str) -> Result<StateProof, Box<dyn Error>> {
+        let value = self.document(hash)?.map(|anchor| anchor.leaf()).unwrap_or_default();
+        self.tree.prove(&document_key(hash), &value)
+    }
+
+    pub fn history(&self, address: &str) -> Result<Vec<HistoryEntry>, Box<dyn Error>> {
+        let mut entries = Vec::new();
+        for item in self.history.scan_prefix(history_prefix(address)) {
+            let (_, data) = item?;
+            entries.push(bincode::deserialize(&data)?);
+        }
+        Ok(entries)
+    }
+
+    // Sceglie, in ordine, le transazioni che si possono includere in `block` (ancora senza transazioni)
+    pub fn select_transactions(&self, candidates: Vec<Transaction>, block: &Block) -> Vec<Transaction> {
+        let mut overlay = Overlay::new(self);
+        candidates.into_iter()
+            .filter(|tx| match overlay.apply(tx, block) {
+                Ok(()) => true,
+                Err(e) => {
+                    println!("   [STATE] ❌ Transaction skipped: {}", e);
+                    false
+                }
+            })
+            .collect()
+    }
+
+    // Perché una transazione in attesa non potrà più entrare in nessun blocco (None se può ancora).
+    // Saldo e finestra di validità possono ancora cambiare: qui solo ciò che un blocco rende definitivo.
+    pub fn invalidation(&self, tx: &Transaction) -> Option<String> {
+        let registered_key = self.public_key(&tx.sender).ok()?;
+        if !verifier::verify(tx, registered_key.as_deref()) {
+            return Some("signature not valid for the current account key".to_string());
+        }
+        if let TransactionKind::AnchorDocument(document) = &tx.kind {
+            if let Ok(Some(anchor)) = self.document(&document.hash) {
+                return Some(format!("document {} is already anchored in block #{}", document.hash, anchor.block_index));
+            }
+        }
+        None
+    }
+
+    // Radice di stato che risulterebbe applicando il blocco (per chi lo produce)
+    pub fn state_root_after(&self, block: &Block) -> Result<String, Box<dyn Error>> {
+        Ok(self.prepare_block(block)?.state_root)
+    }
+
+    // Radice di stato attuale: coincide con quella dell'ultimo blocco salvato
+    pub fn root(&self) -> Result<String, Box<dyn Error>> {
+        Ok(hex::encode(self.tree.root()?))
+    }
+
+    // Applica emissione e transazioni del blocco e lo salva in `db`; se una sola regola fallisce
+    // (o la radice di stato non coincide) il blocco non tocca lo stato.
+    // Stato e blocco si scrivono in un'unica transazione: un crash non li lascia mai disallineati
+    pub fn apply_block(&self, block: &Block, db: &BlockchainDB) -> Result<(), Box<dyn Error>> {
+        let prepared = self.prepare_block(block)?;
+        if prepared.state_root != block.state_root {
+            return Err(format!("state root mismatch: expected {}, block has {}", prepared.state_root, block.state_root).into());
+        }
+
+        let mut meta = Batch::default();
+        meta.insert(CIRCULATING_KEY, &prepared.circulating.to_be_bytes());
+        if block.index == 0 {
+            meta.insert(GENESIS_KEY, block.hash.as_bytes());
+        }
+        let nodes = SparseMerkleTree::batch(prepared.node_updates);
+        let [(blocks, block_batch), (heights, height_batch), (receipts, receipt_batch)] = db.block_writes(block)?;
+        (&self.balances, &self.nonces, &self.public_keys, &self.history, &self.documents, &self.meta, self.tree.nodes(), blocks, heights, receipts)
+            .transaction(|(balances, nonces, public_keys, history, documents, meta_tree, node_tree, blocks, heights, receipts)| {
+                balances.apply_batch(&prepared.balances)?;
+                nonces.apply_batch(&prepared.nonces)?;
+                public_keys.apply_batch(&prepared.public_keys)?;
+                history.apply_batch(&prepared.history)?;
+                documents.apply_batch(&prepared.documents)?;
+                meta_tree.apply_batch(&meta)?;
+                node_tree.apply_batch(&nodes)?;
+                blocks.apply_batch(&block_batch)?;
+                heights.apply_batch(&height_batch)?;
+                receipts.apply_batch(&receipt_batch)?;
+                blocks.flush();
+                Ok::<(), ConflictableTransactionError<sled::Error>>(())
+            })?;
+        Ok(())
+    }
+
+    fn prepare_block(&self, block: &Block) -> Result<PreparedBlock, Box<dyn Error>> {
+        let mut overlay = Overlay::new(self);
+        let mut circulating = self.circulating_supply()?;
+
+        // 1. Allocazioni di genesi: contano nel tetto come qualsiasi altro conio
+        if block.index != 0 && !block.allocations.is_empty() {
+            return Err("allocations are only allowed in the genesis block".into());
+        }
+        if block.index == 0 && self.meta.contains_key(GENESIS_KEY)? {
+            return Err("the genesis block has already been applied".into());
+        }
+        for allocation in &block.allocations {
+            if !address::is_valid(&allocation.address) {
+                return Err(format!("invalid allocation address: {}", allocation.address).into());
+            }
+            circulating = circulating.checked_add(allocation.amount)
+                .filter(|total| *total <= MAX_SUPPLY)
+                .ok_or("genesis allocations exceed the maximum supply")?;
+            overlay.credit(&allocation.address, allocation.amount)?;
+        }
+
+        // 2. Ricompensa di blocco: mai oltre il calendario né oltre il tetto
+        let max_reward = supply::block_reward(block.index, circulating);
+        if block.reward > max_reward {
+            return Err(format!("block reward {} exceeds the allowed {}", block.reward, max_reward).into());
+        }
+        // Ogni blocco dopo la genesi ha un produttore che incassa ricompensa e commissioni:
+        // senza un indirizzo valido finirebbero su un conto "" che nessuno può spendere
+        if block.index != 0 && !address::is_valid(&block.validator) {
+            return Err(format!("block #{} has no valid validator address: '{}'", block.index, block.validator).into());
+        }
+        circulating += block.reward;
+        overlay.credit(&block.validator, block.reward)?;
+
+        // 3. Firme verificate tutte insieme su più core: l'applicazione in ordine trova poi la cache calda.
+        // Le chiavi registrate nello stesso blocco non sono ancora su disco: quelle si verificano dopo.
+        let mut registered_keys = Vec::with_capacity(block.transactions.len());
+        for tx in &block.transactions {
+            registered_keys.push(self.public_key(&tx.sender)?);
+        }
+        let batch: Vec<(&Transaction, Option<&str>)> = block.transactions.iter()
+            .zip(&registered_keys)
+            .map(|(tx, key)| (tx, key.as_deref()))
+            .collect();
+        verifier::verify_batch(&batch);
+
+        for tx in &block.transactions {
+            overlay.apply(tx, block)?;
+        }
+
+        let mut balances = Batch::default();
+        for (address, balance) in &overlay.balances {
+            balances.insert(address.as_bytes(), &balance.to_be_bytes());
+        }
+        let mut nonces = Batch::default();
+        for (address, nonce) in &overlay.nonces {
+            nonces.insert(address.as_bytes(), &nonce.to_be_bytes());
+        }
+        let mut public_keys = Batch::default();
+        for (address, key) in &overlay.public_keys {
+            public_keys.insert(address.as_bytes(), key.as_bytes());
+        }
+
+        let mut documents = Batch::default();
+        for (hash, anchor) in &overlay.documents {
+            documents.insert(hash.as_bytes(), bincode::serialize(anchor)?);
+        }
+
+        let mut history = Batch::default();
+        for (position, allocation) in block.allocations.iter().enumerate() {
+            let minted = HistoryEntry {
+                block_index: block.index,
+                direction: Direction::Mint,
+                counterparty: block.node_id.clone(),
+                amount: allocation.amount,
+                fee: 0,
+            };
+            history.insert(history_key(&allocation.address, block.index, position, &minted.direction), bincode::serialize(&minted)?);
+        }
+        if block.reward > 0 {
+            let minted = HistoryEntry {
+                block_index: block.index,
+                direction: Direction::Mint,
+                counterparty: block.node_id.clone(),
+                amount: block.reward,
+                fee: 0,
+            };
+            let position = block.allocations.len();
+            history.insert(history_key(&block.validator, block.index, position, &minted.direction), bincode::serialize(&minted)?);
+        }
+        for (position, tx) in block.transactions.iter().enumerate() {
+            let (direction, counterparty) = match &tx.kind {
+                TransactionKind::Transfer | TransactionKind::ContractCall { .. } => (Direction::Out, tx.receiver.clone()),
+                TransactionKind::KeyRotation { new_key } => (Direction::KeyRotation, address::from_account_key(new_key)),
+                TransactionKind::CertifyRecord(_) => (Direction::Certification, tx.receiver.clone()),
+                TransactionKind::AnchorDocument(document) => (Direction::DocumentAnchor, document.hash.clone()),
+                TransactionKind::ContractDeploy { .. } => (Direction::ContractDeploy, tx.receiver.clone()),
+            };
+            let sent = HistoryEntry {
+                block_index: block.index,
+                direction,
+                counterparty,
+                amount: tx.amount,
+                fee: tx.fee,
+            };
+            history.insert(history_key(&tx.sender, block.index, position, &sent.direction), bincode::serialize(&sent)?);
+            if !tx.kind.moves_funds() {
+                continue;
+            }
+            let received = HistoryEntry {
+                block_index: block.index,
+                direction: Direction::In,
+                counterparty: tx.sender.clone(),
+                amount: tx.amount,
+                fee: 0,
+            };
+            history.insert(history_key(&tx.receiver, block.index, position, &received.direction), bincode::serialize(&received)?);
+        }
+        let total_fees: u64 = block.transactions.iter().map(|tx| tx.fee).sum();
+        if total_fees > 0 {
+            let earned = HistoryEntry {
+                block_index: block.index,
+                direction: Direction::Fee,
+                counterparty: block.node_id.clone(),
+                amount: total_fees,
+                fee: 0,
+            };
+            let position = block.transactions.len();
+            history.insert(history_key(&block.validator, block.index, position, &earned.direction), bincode::serialize(&earned)?);
+        }
+
+        // Foglie dei conti toccati dal blocco, con saldo e nonce finali
+        let mut touched: Vec<&String> = overlay.balances.keys()
+            .chain(overlay.nonces.keys())
+            .chain(overlay.public_keys.keys())
+            .collect();
+        touched.sort();
+        touched.dedup();
+        let mut leaves = Vec::with_capacity(touched.len());
+        for address in touched {
+            let public_key = overlay.public_key(address)?;
+            let value = Self::account_leaf(overlay.balance(address)?, overlay.nonce(address)?, public_key.as_deref());
+            leaves.push((account_key(address), value));
+        }
+        let mut anchored: Vec<(&String, &DocumentAnchor)> = overlay.documents.iter().collect();
+        anchored.sort_by_key(|(hash, _)| *hash);
+        for (hash, anchor) in anchored {
+            leaves.push((document_key(hash), anchor.leaf()));
+        }
+        let (root, node_updates) = self.tree.prepare(&leaves)?;
+
+        Ok(PreparedBlock {
+            balances,
+            nonces,
+            public_keys,
+            history,
+            documents,
+            circulating,
+            state_root: hex::encode(root),
+            node_updates,
+        })
+    }
+}
+
+// Spazio delle chiavi dello stato: i conti vivono sotto "account/" (in futuro anche i contratti),
+// i documenti notarizzati sotto "document/"
+fn account_key(address: &str) -> String {
+    format!("account/{}", address)
+}
+
+fn document_key(hash: &str) -> String {
+    format!("document/{}", hash)
+}
+
+fn read_u64(tree: &Tree, address: &str) -> Result<u64, Box<dyn Error>> {
+    match tree.get(address)? {
+        Some(bytes) => Ok(u64::from_be_bytes(bytes.as_ref().try_into()?)),
+        None => Ok(0),
+    }
+}
+
+fn history_prefix(address: &str) -> Vec<u8> {
+    let mut key = address.as_bytes().to_vec();
+    key.push(b'/');
+    key
+}
+
+// indirizzo / altezza / posizione / verso: le chiavi restano ordinate per blocco
+fn history_key(address: &str, block_index: u64, position: usize, direction: &Direction) -> Vec<u8> {
+    let mut key = history_prefix(address);
+    key.extend_from_slice(&block_index.to_be_bytes());
+    key.extend_from_slice(&(position as u32).to_be_bytes());
+    key.push(match direction {
+        Direction::Out => 0,
+        Direction::In => 1,
+        Direction::Fee => 2,
+        Direction::Mint => 3,
+        Direction::KeyRotation => 4,
+        Direction::Certification => 5,
+        Direction::ContractDeploy => 6,
+        Direction::DocumentAnchor => 7,
+    });
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notary::{self, DocumentRecord};
+    use crate::supply::Allocation;
+    use crate::wallet::Wallet;
+
+    fn open() -> (BlockchainDB, AccountState) {
+        let db = BlockchainDB::temporary().unwrap();
+        let state = AccountState::open(&db).unwrap();
+        (db, state)
+    }
+
+    // Blocco all'altezza `index` prodotto da `producer`, con ricompensa piena e radice di stato corretta
+    fn block(state: &AccountState, index: u64, producer: &Wallet, transactions: Vec<Transaction>) -> Block {
+        let reward = state.next_block_reward(index).unwrap();
+        let mut block = Block::new(index, String::new(), "test".to_string(), transactions, "TEST".to_string(), producer.address(), reward);
+        block.state_root = state.state_root_after(&block).unwrap();
+        block
+    }
+
+    fn receiver() -> String {
+        Wallet::new().address()
+    }
+
+    #[test]
+    fn overspend_is_rejected() {
+        let (db, state) = open();
+        let alice = Wallet::new();
+        let funding = block(&state, 1, &alice, Vec::new());
+        state.apply_block(&funding, &db).unwrap();
+        let balance = state.balance(&alice.address()).unwrap();
+
+        let overspend = Transaction::new(&alice, receiver(), balance, 1, 0);
+        let mut spending = block(&state, 2, &Wallet::new(), Vec::new());
+        assert!(state.select_transactions(vec![overspend.clone()], &spending).is_empty());
+        spending.transactions = vec![overspend];
+        let err = state.apply_block(&spending, &db).unwrap_err();
+        assert!(err.to_string().contains("insufficient funds"), "{}", err);
+        assert_eq!(state.balance(&alice.address()).unwrap(), balance);
+        assert_eq!(state.nonce(&alice.address()).unwrap(), 0);
+    }
+
+    #[test]
+    fn wrong_or_replayed_nonce_is_rejected() {
+        let (db, state) = open();
+        let (alice, producer) = (Wallet::new(), Wallet::new());
+        state.apply_block(&block(&state, 1, &alice, Vec::new()), &db).unwrap();
+
+        let mut skipped = block(&state, 2, &producer, Vec::new());
+        skipped.transactions = vec![Transaction::new(&alice, receiver(), 10, 1, 1)];
+        let err = state.apply_block(&skipped, &db).unwrap_err();
+        assert!(err.to_string().contains("wrong nonce: expected 0, got 1"), "{}", err);
+
+        let tx = Transaction::new(&alice, receiver(), 10, 1, 0);
+        state.apply_block(&block(&state, 2, &producer, vec![tx.clone()]), &db).unwrap();
+        assert_eq!(state.nonce(&alice.address()).unwrap(), 1);
+
+        let mut replay = block(&state, 3, &producer, Vec::new());
+        replay.transactions = vec![tx];
+        let err = state.apply_block(&replay, &db).unwrap_err();
+        assert!(err.to_string().contains("wrong nonce: expected 1, got 0"), "{}", err);
+    }
+
+    #[test]
+    fn fee_is_credited_to_the_validator() {
+        let (db, state) = open();
+        let (alice, producer) = (Wallet::new(), Wallet::new());
+        state.apply_block(&block(&state, 1, &alice, Vec::new()), &db).unwrap();
+        let funded = state.balance(&alice.address()).unwrap();
+
+        let to = receiver();
+        let paying = block(&state, 2, &producer, vec![Transaction::new(&alice, to.clone(), 100, 7, 0)]);
+        state.apply_block(&paying, &db).unwrap();
+        assert_eq!(state.balance(&producer.address()).unwrap(), paying.reward + 7);
+        assert_eq!(state.balance(&alice.address()).unwrap(), funded - 107);
+        assert_eq!(state.balance(&to).unwrap(), 100);
+        let fees: Vec<HistoryEntry> = state.history(&producer.address()).unwrap().into_iter()
+            .filter(|entry| entry.direction == Direction::Fee)
+            .collect();
+        assert_eq!(fees.len(), 1);
+        assert_eq!(fees[0].amount, 7);
+    }
+
+    #[test]
+    fn rotated_key_refuses_the_old_one() {
+        let (db, state) = open();
+        let (alice, new_key, producer) = (Wallet::new(), Wallet::new(), Wallet::new());
+        let account = alice.address();
+        state.apply_block(&block(&state, 1, &alice, Vec::new()), &db).unwrap();
+
+        let rotation = Transaction::new_key_rotation(account.clone(), &alice, new_key.public_key.clone(), 1, 0).unwrap();
+        state.apply_block(&block(&state, 2, &producer, vec![rotation]), &db).unwrap();
+        assert_eq!(state.public_key(&account).unwrap(), Some(new_key.public_key.clone()));
+
+        let old = Transaction::new(&alice, receiver(), 10, 1, 1);
+        assert!(state.invalidation(&old).is_some());
+        let mut refused = block(&state, 3, &producer, Vec::new());
+        refused.transactions = vec![old];
+        let err = state.apply_block(&refused, &db).unwrap_err();
+        assert!(err.to_string().contains("invalid signature"), "{}", err);
+
+        let mut rotated = Transaction::unsigned(account.clone(), receiver(), 10, 1, 1);
+        rotated.sign(&new_key).unwrap();
+        state.apply_block(&block(&state, 3, &producer, vec![rotated]), &db).unwrap();
+        assert_eq!(state.nonce(&account).unwrap(), 2);
+    }
+
+    #[test]
+    fn document_is_anchored_only_once() {
+        let (db, state) = open();
+        let (alice, bob, producer) = (Wallet::new(), Wallet::new(), Wallet::new());
+        state.apply_block(&block(&state, 1, &alice, Vec::new()), &db).unwrap();
+        state.apply_block(&block(&state, 2, &bob, Vec::new()), &db).unwrap();
+        let hash = notary::hash_file(b"DDT-2024-118");
+        let anchor = |wallet: &Wallet| {
+            let mut tx = Transaction::unsigned(wallet.address(), wallet.address(), 0, 1, 0);
+            tx.kind = TransactionKind::AnchorDocument(DocumentRecord::new(hash.clone()));
+            tx.sign(wallet).unwrap();
+            tx
+        };
+
+        // Due ancoraggi della stessa impronta nello stesso blocco: passa solo il primo
+        let candidate = block(&state, 3, &producer, Vec::new());
+        assert_eq!(state.select_transactions(vec![anchor(&alice), anchor(&bob)], &candidate).len(), 1);
+
+        state.apply_block(&block(&state, 3, &producer, vec![anchor(&alice)]), &db).unwrap();
+        assert_eq!(state.document(&hash).unwrap().unwrap().owner, alice.address());
+        assert!(state.invalidation(&anchor(&bob)).is_some());
+        let mut again = block(&state, 4, &producer, Vec::new());
+        again.transactions = vec![anchor(&bob)];
+        let err = state.apply_block(&again, &db).unwrap_err();
+        assert!(err.to_string().contains("is already anchored in block #3"), "{}", err);
+        assert_eq!(state.document(&hash).unwrap().unwrap().owner, alice.address());
+    }
+
+    #[test]
+    fn state_root_mismatch_rejects_the_block() {
+        let (db, state) = open();
+        let producer = Wallet::new();
+        let root = state.root().unwrap();
+        let mut forged = block(&state, 1, &producer, Vec::new());
+        forged.state_root = "00".repeat(32);
+        let err = state.apply_block(&forged, &db).unwrap_err();
+        assert!(err.to_string().contains("state root mismatch"), "{}", err);
+        // Nulla è stato scritto: né lo stato né il blocco
+        assert_eq!(state.root().unwrap(), root);
+        assert_eq!(state.balance(&producer.address()).unwrap(), 0);
+        assert!(db.load_tip().unwrap().is_none());
+    }
+
+    #[test]
+    fn genesis_allocations_are_applied_once() {
+        let (db, state) = open();
+        let (alice, producer) = (Wallet::new(), Wallet::new());
+        let allocations = vec![Allocation { address: alice.address(), amount: 5_000 }];
+        let mut genesis = Block::genesis(allocations.clone());
+        genesis.state_root = state.state_root_after(&genesis).unwrap();
+        state.apply_block(&genesis, &db).unwrap();
+        assert_eq!(state.balance(&alice.address()).unwrap(), 5_000);
+        assert_eq!(state.circulating_supply().unwrap(), 5_000);
+
+        // La stessa genesi una seconda volta
+        assert!(state.state_root_after(&genesis).is_err());
+        assert!(state.apply_block(&genesis, &db).is_err());
+        // Allocazioni in un blocco successivo
+        let mut later = block(&state, 1, &producer, Vec::new());
+        later.allocations = allocations;
+        let err = state.apply_block(&later, &db).unwrap_err();
+        assert!(err.to_string().contains("only allowed in the genesis block"), "{}", err);
+
+        assert_eq!(state.balance(&alice.address()).unwrap(), 5_000);
+        assert_eq!(state.circulating_supply().unwrap(), 5_000);
+    }
+}
@@ -0,0 +1,120 @@
+// Politica monetaria di Adamas: offerta massima = 35° numero di Lucas (L35)
+use serde::{Deserialize, Serialize};
+
+// Unità minima: 1 ADM = 10^8 unità. Tutti gli importi on-chain sono in unità.
+pub const DECIMALS: u32 = 8;
+pub const UNITS_PER_ADM: u64 = 10u64.pow(DECIMALS);
+
+// 20.633.239 ADM, mai superabile (allocazioni di genesi + ricompense di blocco)
+pub const MAX_SUPPLY_ADM: u64 = 20_633_239;
+pub const MAX_SUPPLY: u64 = MAX_SUPPLY_ADM * UNITS_PER_ADM;
+
+// Ricompensa iniziale per blocco: 5 ADM
+pub const INITIAL_BLOCK_REWARD: u64 = 5 * UNITS_PER_ADM;
+
+// Ogni era la ricompensa scende di 610/987 (rapporto di Fibonacci ~ 1/φ)
+const DECAY_NUMERATOR: u64 = 610;
+const DECAY_DENOMINATOR: u64 = 987;
+
+// Durata di un'era ricavata dal tetto: genesi + tutte le ere arrivano a MAX_SUPPLY
+// (resta fuori meno di una ricompensa per blocco sommata su tutte le ere, ~13 ADM)
+pub const BLOCKS_PER_ERA: u64 = (MAX_SUPPLY - genesis_total()) / reward_per_era_block();
+
+// Somma delle ricompense di un blocco per ogni era, finché la ricompensa non si azzera
+const fn reward_per_era_block() -> u64 {
+    let mut reward = INITIAL_BLOCK_REWARD;
+    let mut total = 0;
+    while reward > 0 {
+        total += reward;
+        reward = reward * DECAY_NUMERATOR / DECAY_DENOMINATOR;
+    }
+    total
+}
+
+const fn genesis_total() -> u64 {
+    let mut total = 0;
+    let mut i = 0;
+    while i < GENESIS_ALLOCATIONS.len() {
+        total += GENESIS_ALLOCATIONS[i].1;
+        i += 1;
+    }
+    total
+}
+
+// Offerta totale che il calendario di emissione produrrà davvero (allocazioni di genesi comprese)
+pub const MINTABLE_SUPPLY: u64 = genesis_total() + BLOCKS_PER_ERA * reward_per_era_block();
+
+// Saldo assegnato nel blocco di genesi (conta nell'offerta come le ricompense)
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Allocation {
+    pub address: String,
+    pub amount: u64,
+}
+
+// Allocazioni di genesi (indirizzo, unità): regola di consenso, per questo stanno nel codice e non
+// in node_config.json. Nodi con allocazioni diverse avrebbero stati diversi e rifiuterebbero i blocchi
+// gli uni degli altri. Vuoto = tutta l'offerta arriva dalle ricompense di blocco.
+const GENESIS_ALLOCATIONS: &[(&str, u64)] = &[];
+
+pub fn genesis_allocations() -> Vec<Allocation> {
+    GENESIS_ALLOCATIONS.iter()
+        .map(|(address, amount)| Allocation { address: address.to_string(), amount: *amount })
+        .collect()
+}
+
+// Ricompensa prevista dal calendario di emissione, senza considerare il tetto
+pub fn scheduled_reward(height: u64) -> u64 {
+    if height == 0 {
+        return 0;
+    }
+    let mut reward = INITIAL_BLOCK_REWARD;
+    for _ in 0..(height - 1) / BLOCKS_PER_ERA {
+        reward = reward * DECAY_NUMERATOR / DECAY_DENOMINATOR;
+        if reward == 0 {
+            break;
+        }
+    }
+    reward
+}
+
+// Ricompensa massima per il blocco `height`, tagliata su ciò che resta sotto il tetto
+pub fn block_reward(height: u64, circulating: u64) -> u64 {
+    scheduled_reward(height).min(MAX_SUPPLY.saturating_sub(circulating))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reward_decays_by_era() {
+        assert_eq!(scheduled_reward(0), 0);
+        assert_eq!(scheduled_reward(1), INITIAL_BLOCK_REWARD);
+        assert_eq!(scheduled_reward(BLOCKS_PER_ERA), INITIAL_BLOCK_REWARD);
+        let second = INITIAL_BLOCK_REWARD * DECAY_NUMERATOR / DECAY_DENOMINATOR;
+        assert_eq!(scheduled_reward(BLOCKS_PER_ERA + 1), second);
+        assert_eq!(scheduled_reward(2 * BLOCKS_PER_ERA + 1), second * DECAY_NUMERATOR / DECAY_DENOMINATOR);
+        // Prima o poi la ricompensa si azzera, e l'altezza massima non va in loop
+        assert_eq!(scheduled_reward(u64::MAX), 0);
+    }
+
+    #[test]
+    fn emission_reaches_the_cap() {
+        let mut total: u64 = genesis_total();
+        let mut era = 0;
+        loop {
+            let reward = scheduled_reward(era * BLOCKS_PER_ERA + 1);
+            if reward == 0 {
+                break;
+            }
+            total += reward * BLOCKS_PER_ERA;
+            era += 1;
+        }
+        assert_eq!(total, MINTABLE_SUPPLY);
+        assert!(total <= MAX_SUPPLY);
+        // Il tetto non è decorativo: resta fuori meno di una ricompensa per era
+        assert!(MAX_SUPPLY - total < reward_per_era_block());
+        assert_eq!(block_reward(1, MAX_SUPPLY - 10), 10);
+        assert_eq!(block_reward(1, MAX_SUPPLY), 0);
+    }
+}
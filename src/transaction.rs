@@ -1,30 +1,301 @@
+use crate::address;
+use crate::certification::CertificationRecord;
+use crate::multisig::{MultisigPolicy, PartialSignature};
+use crate::notary::DocumentRecord;
+use crate::signer::Signer;
 use crate::wallet::Wallet;
 use serde::{Serialize, Deserialize};
+use sha3::{Digest, Sha3_256};
+use std::cmp::Ordering;
+use std::error::Error;
+
+// Limiti dei contenuti: una transazione deve restare piccola anche con una firma post-quantum
+pub const MAX_METHOD_BYTES: usize = 64;
+pub const MAX_CONTRACT_CODE_BYTES: usize = 64 * 1024;
+pub const MAX_CONTRACT_ARGS_BYTES: usize = 4 * 1024;
+
+// Cosa significa la transazione. Ogni tipo ha le sue regole (vedi `validate`) e fa parte del messaggio firmato.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub enum TransactionKind {
+    #[default]
+    Transfer,
+    // Sostituisce la chiave autorizzata del mittente (chiave singola o politica multisig).
+    // Indirizzo, saldo e storico restano; importo 0 verso sé stessi.
+    KeyRotation { new_key: String },
+    // Record di certificazione firmato dal mittente, importo 0 verso sé stessi
+    CertifyRecord(CertificationRecord),
+    // Impronta di un documento con i suoi metadati, importo 0 verso sé stessi.
+    // Ogni impronta si ancora una sola volta: vale il primo blocco che la contiene.
+    AnchorDocument(DocumentRecord),
+    // Codice WASM (hex) per l'AVM. Il destinatario è l'indirizzo del contratto (vedi address::contract_address),
+    // l'importo è il saldo iniziale. L'AVM non esegue ancora nulla: la catena registra il contratto.
+    ContractDeploy { code: String },
+    // Chiamata a un contratto (il destinatario); l'importo viene trasferito al contratto
+    ContractCall { method: String, args: String },
+}
+
+impl TransactionKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            TransactionKind::Transfer => "transfer",
+            TransactionKind::KeyRotation { .. } => "key-rotation",
+            TransactionKind::CertifyRecord(_) => "certify-record",
+            TransactionKind::AnchorDocument(_) => "anchor-document",
+            TransactionKind::ContractDeploy { .. } => "contract-deploy",
+            TransactionKind::ContractCall { .. } => "contract-call",
+        }
+    }
+
+    // Forma canonica (JSON) usata nel messaggio firmato e nell'hash del blocco
+    pub fn encode(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    // Trasferimenti e contratti spostano fondi verso il destinatario; gli altri tipi sono verso sé stessi
+    pub fn moves_funds(&self) -> bool {
+        matches!(self, TransactionKind::Transfer | TransactionKind::ContractDeploy { .. } | TransactionKind::ContractCall { .. })
+    }
+}
+
+// Limite di una finestra di validità: altezza del blocco oppure tempo UNIX in secondi (timestamp del blocco)
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ValidityBound {
+    Height(u64),
+    Time(u64),
+}
+
+impl ValidityBound {
+    // "height:N" oppure "time:N", come nel messaggio firmato
+    pub fn parse(text: &str) -> Result<Self, Box<dyn Error>> {
+        match text.split_once(':') {
+            Some(("height", value)) => Ok(ValidityBound::Height(value.parse()?)),
+            Some(("time", value)) => Ok(ValidityBound::Time(value.parse()?)),
+            _ => Err(format!("validity bound must be height:N or time:N, got '{}'", text).into()),
+        }
+    }
+
+    pub fn encode(&self) -> String {
+        match self {
+            ValidityBound::Height(height) => format!("height:{}", height),
+            ValidityBound::Time(time) => format!("time:{}", time),
+        }
+    }
+
+    // Posizione di un blocco all'altezza `height` con timestamp `time` (secondi) rispetto al limite
+    fn compare(&self, height: u64, time: u64) -> Ordering {
+        match self {
+            ValidityBound::Height(bound) => height.cmp(bound),
+            ValidityBound::Time(bound) => time.cmp(bound),
+        }
+    }
+}
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Transaction {
-    pub sender: String,
-    pub receiver: String,
+    pub sender: String,   // Indirizzo breve (vedi address.rs)
+    pub receiver: String, // Indirizzo breve
     pub amount: u64,
-    pub signature: String,
+    pub fee: u64,   // Commissione pagata a chi produce il blocco
+    pub nonce: u64, // Contatore per mittente: impedisce di riusare la stessa firma
+    // Chiave del conto: chiave pubblica Dilithium (hex) oppure politica multisig codificata.
+    // Serve solo alla prima spesa, poi resta registrata on-chain e si può lasciare vuota.
+    // Non fa parte del messaggio firmato.
+    pub public_key: String,
+    pub signature: String,                 // Conti a firma singola
+    pub signatures: Vec<PartialSignature>, // Conti multifirma
+    #[serde(default)]
+    pub kind: TransactionKind,
+    // Finestra di validità (limiti inclusi), parte del messaggio firmato: una transazione firmata oggi
+    // non resta includibile per sempre
+    #[serde(default)]
+    pub valid_after: Option<ValidityBound>,
+    #[serde(default)]
+    pub valid_until: Option<ValidityBound>,
 }
 
 impl Transaction {
-    pub fn new(sender_wallet: &Wallet, receiver: String, amount: u64) -> Self {
-        let sender_pub = sender_wallet.public_key.clone();
-        let data_to_sign = format!("{}{}{}", sender_pub, receiver, amount);
-        let signature = sender_wallet.sign(&data_to_sign);
+    pub fn new(sender_wallet: &Wallet, receiver: String, amount: u64, fee: u64, nonce: u64) -> Self {
+        let mut tx = Self::unsigned(sender_wallet.address(), receiver, amount, fee, nonce);
+        tx.sign(sender_wallet).expect("Key Error");
+        tx
+    }
 
+    // Transazione ancora senza firme. Il mittente è esplicito: dopo una rotazione
+    // l'indirizzo del conto non coincide più con quello della chiave che firma.
+    pub fn unsigned(sender: String, receiver: String, amount: u64, fee: u64, nonce: u64) -> Self {
         Transaction {
-            sender: sender_pub,
+            sender,
             receiver,
             amount,
-            signature,
+            fee,
+            nonce,
+            public_key: String::new(),
+            signature: String::new(),
+            signatures: Vec::new(),
+            kind: TransactionKind::Transfer,
+            valid_after: None,
+            valid_until: None,
+        }
+    }
+
+    // Sostituisce la chiave autorizzata di `sender` con `new_key`; firma la chiave attuale
+    pub fn new_key_rotation(sender: String, current_key: &dyn Signer, new_key: String, fee: u64, nonce: u64) -> Result<Self, Box<dyn Error>> {
+        let mut tx = Self::unsigned(sender.clone(), sender, 0, fee, nonce);
+        tx.kind = TransactionKind::KeyRotation { new_key };
+        tx.sign(current_key)?;
+        Ok(tx)
+    }
+
+    // Firma con una chiave singola (la chiave pubblica viaggia con la transazione).
+    // Il firmatario può essere un wallet locale o un processo esterno (vedi signer.rs).
+    pub fn sign(&mut self, signer: &dyn Signer) -> Result<(), Box<dyn Error>> {
+        self.signature = signer.sign_message(&self.payload())?;
+        self.public_key = signer.public_key().to_string();
+        Ok(())
+    }
+
+    // Transazione da un conto multifirma, ancora senza firme: passa di mano in mano
+    // (è serializzabile) e ogni firmatario aggiunge la sua con `add_signature`
+    pub fn new_multisig(policy: &MultisigPolicy, receiver: String, amount: u64, fee: u64, nonce: u64) -> Self {
+        let mut tx = Self::unsigned(policy.address(), receiver, amount, fee, nonce);
+        tx.public_key = policy.encode();
+        tx
+    }
+
+    // Aggiunge (o sostituisce) la firma parziale di `key`, che deve far parte della politica
+    pub fn add_signature(&mut self, policy: &MultisigPolicy, key: &dyn Signer) -> Result<(), Box<dyn Error>> {
+        if policy.encode() != self.public_key {
+            return Err("the policy does not match the transaction account key".into());
         }
+        let signer = policy.signer_index(key.public_key()).ok_or("this wallet is not a signer of the multisig")?;
+        let signature = key.sign_message(&self.payload())?;
+
+        self.signatures.retain(|partial| partial.signer != signer);
+        self.signatures.push(PartialSignature { signer, signature });
+        self.signatures.sort_by_key(|partial| partial.signer);
+        Ok(())
     }
 
-    pub fn verify(&self) -> bool {
-        let data_to_check = format!("{}{}{}", self.sender, self.receiver, self.amount);
-        Wallet::verify(&data_to_check, &self.signature, &self.sender)
+    // Regole del tipo di transazione (firma, nonce e saldo si controllano nello stato)
+    pub fn validate(&self) -> Result<(), Box<dyn Error>> {
+        if let (Some(after), Some(until)) = (self.valid_after, self.valid_until) {
+            let empty = match (after, until) {
+                (ValidityBound::Height(after), ValidityBound::Height(until)) | (ValidityBound::Time(after), ValidityBound::Time(until)) => after > until,
+                _ => false,
+            };
+            if empty {
+                return Err(format!("empty validity window: {} is after {}", after.encode(), until.encode()).into());
+            }
+        }
+        if !self.kind.moves_funds() && (self.receiver != self.sender || self.amount != 0) {
+            return Err(format!("a {} transaction must send 0 to the sender itself", self.kind.name()).into());
+        }
+        match &self.kind {
+            TransactionKind::Transfer => {}
+            TransactionKind::KeyRotation { new_key } => {
+                if !address::is_valid_account_key(new_key) {
+                    return Err("the new key is neither a supported public key nor a multisig policy".into());
+                }
+            }
+            TransactionKind::CertifyRecord(record) => record.validate()?,
+            TransactionKind::AnchorDocument(document) => document.validate()?,
+            TransactionKind::ContractDeploy { code } => {
+                let bytes = hex::decode(code).map_err(|_| "contract code must be hex")?;
+                if bytes.is_empty() || bytes.len() > MAX_CONTRACT_CODE_BYTES {
+                    return Err(format!("contract code must be 1 to {} bytes", MAX_CONTRACT_CODE_BYTES).into());
+                }
+                if self.receiver != address::contract_address(&self.sender, self.nonce) {
+                    return Err("a contract deploy must target the contract address of sender and nonce".into());
+                }
+            }
+            TransactionKind::ContractCall { method, args } => {
+                if method.is_empty() || method.len() > MAX_METHOD_BYTES || !method.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                    return Err("contract method must be a non-empty identifier".into());
+                }
+                if args.len() > MAX_CONTRACT_ARGS_BYTES {
+                    return Err(format!("contract call arguments exceed {} bytes", MAX_CONTRACT_ARGS_BYTES).into());
+                }
+            }
+        }
+        Ok(())
     }
-}
\ No newline at end of file
+
+    // Finestra di validità per un blocco all'altezza `height` con timestamp `time` (secondi)
+    pub fn check_window(&self, height: u64, time: u64) -> Result<(), Box<dyn Error>> {
+        if let Some(bound) = self.valid_after {
+            if bound.compare(height, time) == Ordering::Less {
+                return Err(format!("transaction not valid before {}", bound.encode()).into());
+            }
+        }
+        if self.is_expired(height, time) {
+            return Err(format!("transaction expired after {}", self.valid_until.map(|bound| bound.encode()).unwrap_or_default()).into());
+        }
+        Ok(())
+    }
+
+    // Oltre `valid_until`: non potrà più entrare in nessun blocco
+    pub fn is_expired(&self, height: u64, time: u64) -> bool {
+        self.valid_until.is_some_and(|bound| bound.compare(height, time) == Ordering::Greater)
+    }
+
+    // I separatori evitano ambiguità tra campi numerici adiacenti (es. 12|3 e 1|23).
+    // Tipo e finestra di validità si aggiungono solo se presenti: i trasferimenti semplici firmano lo stesso testo di prima.
+    fn payload(&self) -> String {
+        let mut payload = format!("{}|{}|{}|{}|{}", self.sender, self.receiver, self.amount, self.fee, self.nonce);
+        if self.kind != TransactionKind::Transfer {
+            payload.push_str("|kind:");
+            payload.push_str(&self.kind.encode());
+        }
+        if let Some(bound) = self.valid_after {
+            payload.push_str("|valid_after:");
+            payload.push_str(&bound.encode());
+        }
+        if let Some(bound) = self.valid_until {
+            payload.push_str("|valid_until:");
+            payload.push_str(&bound.encode());
+        }
+        payload
+    }
+
+    // ID della transazione: hash del contenuto firmato, senza chiave né firme.
+    // Resta lo stesso con qualsiasi schema di firma e mentre un multisig raccoglie le firme.
+    pub fn id(&self) -> String {
+        hex::encode(Sha3_256::digest(self.payload().as_bytes()))
+    }
+
+    // Verifica la firma con la chiave registrata per il mittente, oppure (prima spesa)
+    // con la chiave allegata, che deve corrispondere all'indirizzo del mittente.
+    // Per i conti multifirma servono almeno `threshold` firme parziali valide.
+    pub fn verify(&self, registered_key: Option<&str>) -> bool {
+        let key = match registered_key {
+            Some(key) => {
+                if !self.public_key.is_empty() && self.public_key != key {
+                    return false;
+                }
+                key
+            }
+            None => {
+                if self.public_key.is_empty() || address::from_account_key(&self.public_key) != self.sender {
+                    return false;
+                }
+                &self.public_key
+            }
+        };
+        let data_to_check = self.payload();
+        match MultisigPolicy::decode(key) {
+            Some(policy) => policy.verify(&data_to_check, &self.signatures),
+            None => Wallet::verify(&data_to_check, &self.signature, key),
+        }
+    }
+
+    // Peso della transazione in byte (serializzata come sul disco)
+    pub fn size(&self) -> u64 {
+        bincode::serialized_size(self).unwrap_or(u64::MAX)
+    }
+
+    // Commissione ogni 1000 byte: le firme post-quantum pesano, quindi si paga lo spazio
+    pub fn fee_rate(&self) -> u64 {
+        self.fee.saturating_mul(1000) / self.size().max(1)
+    }
+}
@@ -0,0 +1,102 @@
+// Verifica delle firme in parallelo + cache delle firme già verificate.
+// Una transazione viene controllata all'ingresso in mempool e di nuovo quando il blocco
+// che la contiene viene prodotto o ricevuto: con la cache il lavoro crittografico si fa una volta sola.
+use crate::transaction::Transaction;
+use rayon::prelude::*;
+use sha3::{Digest, Sha3_256};
+use std::collections::{HashSet, VecDeque};
+use std::sync::{Mutex, OnceLock};
+
+// Circa 64 byte per voce: la cache resta sotto i pochi MB
+pub const CACHE_CAPACITY: usize = 50_000;
+
+type CacheKey = [u8; 32];
+
+// Solo le verifiche riuscite: una firma invalida non deve occupare posto
+struct VerifiedCache {
+    seen: HashSet<CacheKey>,
+    order: VecDeque<CacheKey>, // Ordine di inserimento, per scartare le voci più vecchie
+}
+
+fn cache() -> &'static Mutex<VerifiedCache> {
+    static CACHE: OnceLock<Mutex<VerifiedCache>> = OnceLock::new();
+    CACHE.get_or_init(|| Mutex::new(VerifiedCache { seen: HashSet::new(), order: VecDeque::new() }))
+}
+
+// Hash della transazione (firme comprese) + chiave con cui è stata verificata.
+// L'ID copre tutto il contenuto firmato (finestre di validità comprese), il resto sono chiave e firme.
+fn cache_key(tx: &Transaction, registered_key: Option<&str>) -> CacheKey {
+    let mut hasher = Sha3_256::new();
+    // Campi a lunghezza variabile preceduti dalla lunghezza: nessuna ambiguità tra campi adiacenti
+    for field in [tx.id().as_str(), tx.public_key.as_str(), tx.signature.as_str()] {
+        hasher.update((field.len() as u64).to_be_bytes());
+        hasher.update(field.as_bytes());
+    }
+    for partial in &tx.signatures {
+        hasher.update((partial.signer as u64).to_be_bytes());
+        hasher.update((partial.signature.len() as u64).to_be_bytes());
+        hasher.update(partial.signature.as_bytes());
+    }
+    match registered_key {
+        Some(key) => {
+            hasher.update([1u8]);
+            hasher.update(key.as_bytes());
+        }
+        None => hasher.update([0u8]),
+    }
+    hasher.finalize().into()
+}
+
+fn is_cached(key: &CacheKey) -> bool {
+    cache().lock().unwrap().seen.contains(key)
+}
+
+fn remember(key: CacheKey) {
+    let mut cache = cache().lock().unwrap();
+    if !cache.seen.insert(key) {
+        return;
+    }
+    cache.order.push_back(key);
+    while cache.order.len() > CACHE_CAPACITY {
+        if let Some(oldest) = cache.order.pop_front() {
+            cache.seen.remove(&oldest);
+        }
+    }
+}
+
+// Come `Transaction::verify`, ma salta il controllo se la stessa firma è già stata verificata
+pub fn verify(tx: &Transaction, registered_key: Option<&str>) -> bool {
+    let key = cache_key(tx, registered_key);
+    if is_cached(&key) {
+        return true;
+    }
+    let valid = tx.verify(registered_key);
+    if valid {
+        remember(key);
+    }
+    valid
+}
+
+// Verifica un lotto di transazioni su tutti i core disponibili (pool di thread persistente) (una chiave registrata per transazione).
+// I risultati sono nello stesso ordine dell'input.
+pub fn verify_batch(items: &[(&Transaction, Option<&str>)]) -> Vec<bool> {
+    let keys: Vec<CacheKey> = items.iter().map(|(tx, key)| cache_key(tx, *key)).collect();
+    let mut results: Vec<bool> = keys.iter().map(is_cached).collect();
+    let pending: Vec<usize> = (0..items.len()).filter(|&i| !results[i]).collect();
+    if pending.is_empty() {
+        return results;
+    }
+
+    // Pool globale di rayon: i thread restano vivi tra un lotto e l'altro
+    let verified: Vec<(usize, bool)> = pending.par_iter()
+        .map(|&i| (i, items[i].0.verify(items[i].1)))
+        .collect();
+
+    for (i, valid) in verified {
+        results[i] = valid;
+        if valid {
+            remember(keys[i]);
+        }
+    }
+    results
+}
@@ -1,51 +1,146 @@
-use pqcrypto_dilithium::dilithium5::*; 
-use pqcrypto_traits::sign::{SecretKey as _, PublicKey as _, DetachedSignature as _};
+use crate::address;
+use crate::keystore::Keystore;
+use crate::mnemonic;
+use crate::signature::{self, Algorithm};
+use std::error::Error;
+use zeroize::Zeroize;
 
 pub struct Wallet {
-    pub public_key: String,
+    pub algorithm: Algorithm,
+    pub public_key: String, // hex(id algoritmo ‖ chiave), vedi signature.rs
     secret_key_bytes: Box<[u8]>,
 }
 
 impl Wallet {
     pub fn new() -> Self {
-        println!("   [CRYPTO] Initializing Post-Quantum Engine (Dilithium-5)...");
-        
-        let (pk, sk) = keypair();
-        let pk_hex = hex::encode(pk.as_bytes());
-        
-        Wallet {
-            public_key: pk_hex,
-            secret_key_bytes: Box::from(sk.as_bytes()),
+        Self::generate(signature::DEFAULT_ALGORITHM)
+    }
+
+    pub fn generate(algorithm: Algorithm) -> Self {
+        println!("   [CRYPTO] Initializing Post-Quantum Engine ({})...", algorithm.name());
+
+        let (pk, mut sk) = algorithm.keypair();
+        let wallet = Wallet {
+            algorithm,
+            public_key: signature::encode(algorithm, &pk),
+            secret_key_bytes: Box::from(sk.as_slice()),
+        };
+        sk.zeroize();
+        wallet
+    }
+
+    // Coppia di chiavi deterministica: stesso seed, stesso wallet (solo Dilithium-5)
+    pub fn from_seed(seed: &[u8; 32]) -> Result<Self, Box<dyn Error>> {
+        let (public_key, secret_key) = signature::dilithium5_seeded::keypair(seed);
+        let wallet = Wallet {
+            algorithm: Algorithm::Dilithium5,
+            public_key: signature::encode(Algorithm::Dilithium5, &public_key),
+            secret_key_bytes: Box::from(secret_key.as_slice()),
+        };
+
+        // Le chiavi devono essere utilizzabili dal motore di firma del nodo
+        if !Algorithm::Dilithium5.is_valid_secret_key(&wallet.secret_key_bytes) {
+            return Err("derived key is not a valid Dilithium-5 secret key".into());
+        }
+        let probe = "ADAMAS-KEYGEN-CHECK";
+        if !Wallet::verify(probe, &wallet.sign(probe), &wallet.public_key) {
+            return Err("derived Dilithium-5 keypair failed the signature check".into());
+        }
+        Ok(wallet)
+    }
+
+    // Ricostruisce il conto `path` (es. "m/0", "m/1") da una frase di recupero
+    pub fn from_mnemonic(phrase: &str, passphrase: &str, path: &str) -> Result<Self, Box<dyn Error>> {
+        let seed = mnemonic::derive_seed(phrase, passphrase, path)?;
+        Wallet::from_seed(&seed)
+    }
+
+    // Nuova frase di 24 parole + wallet del percorso di default
+    pub fn generate_mnemonic(passphrase: &str) -> Result<(String, Self), Box<dyn Error>> {
+        let phrase = mnemonic::generate_phrase()?;
+        let wallet = Wallet::from_mnemonic(&phrase, passphrase, mnemonic::DEFAULT_PATH)?;
+        Ok((phrase, wallet))
+    }
+
+    // Indirizzo breve derivato dalla chiave pubblica
+    pub fn address(&self) -> String {
+        address::from_public_key(&self.public_key)
+    }
+
+    // Salva la chiave in un keystore cifrato con la passphrase
+    pub fn save_keystore(&self, path: &str, passphrase: &str) -> Result<(), Box<dyn Error>> {
+        Keystore::encrypt(&self.address(), &self.public_key, &self.secret_key_bytes, passphrase)?.save(path)
+    }
+
+    // Ricarica un wallet salvato con `save_keystore`
+    pub fn load_keystore(path: &str, passphrase: &str) -> Result<Self, Box<dyn Error>> {
+        let keystore = Keystore::load(path)?;
+        let secret_key = keystore.decrypt(passphrase)?;
+        let algorithm = signature::algorithm_of(&keystore.public_key).ok_or("keystore public key has an unknown format")?;
+        if !algorithm.is_valid_secret_key(&secret_key) {
+            return Err(format!("keystore does not contain a {} secret key", algorithm.name()).into());
+        }
+        if address::from_public_key(&keystore.public_key) != keystore.address {
+            return Err("keystore address does not match its public key".into());
         }
+        Ok(Wallet {
+            algorithm,
+            public_key: keystore.public_key,
+            secret_key_bytes: Box::from(secret_key.as_slice()),
+        })
     }
 
     pub fn sign(&self, message: &str) -> String {
-        let sk = SecretKey::from_bytes(&self.secret_key_bytes).expect("Key Error");
-        let signature = detached_sign(message.as_bytes(), &sk);
-        hex::encode(signature.as_bytes())
+        let signature = self.algorithm.sign(message.as_bytes(), &self.secret_key_bytes).expect("Key Error");
+        signature::encode(self.algorithm, &signature)
     }
 
+    // Firma e chiave pubblica devono dichiarare lo stesso algoritmo
     pub fn verify(message: &str, signature_hex: &str, public_key_hex: &str) -> bool {
-        let pk_bytes = match hex::decode(public_key_hex) {
-            Ok(b) => b,
-            Err(_) => return false,
-        };
-        
-        let sig_bytes = match hex::decode(signature_hex) {
-            Ok(b) => b,
-            Err(_) => return false,
-        };
+        signature::verify(message, signature_hex, public_key_hex)
+    }
+}
 
-        let pk = match PublicKey::from_bytes(&pk_bytes) {
-            Ok(k) => k,
-            Err(_) => return false,
-        };
+impl Default for Wallet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
-        let sig = match DetachedSignature::from_bytes(&sig_bytes) {
-            Ok(s) => s,
-            Err(_) => return false,
-        };
+// La chiave segreta viene azzerata in memoria quando il wallet viene distrutto
+impl Drop for Wallet {
+    fn drop(&mut self) {
+        self.secret_key_bytes.zeroize();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sha3::{Digest, Sha3_256};
 
-        verify_detached_signature(&sig, message.as_bytes(), &pk).is_ok()
+    const PHRASE: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon \
+                          abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon art";
+
+    // Ricavato anche con crypto_sign_keypair di PQClean (dilithium5/clean) e randombytes = seed di m/0:
+    // se cambia, le frasi di recupero esistenti non ritrovano più i loro conti
+    #[test]
+    fn mnemonic_test_vector() {
+        let wallet = Wallet::from_mnemonic(PHRASE, "", mnemonic::DEFAULT_PATH).unwrap();
+        assert_eq!(wallet.address(), "admbf993a90331f9dc4b1ea4f2b42cf210e7b1bfdb6b8922ac6");
+        assert_eq!(
+            hex::encode(Sha3_256::digest(wallet.public_key.as_bytes())),
+            "6ba709473420b90f5413e35044d9e7892305d531dd780be69cdc7cb764ad023e"
+        );
+    }
+
+    #[test]
+    fn derived_keys_sign_with_the_node_engine() {
+        let first = Wallet::from_mnemonic(PHRASE, "", "m/0").unwrap();
+        let second = Wallet::from_mnemonic(PHRASE, "", "m/1").unwrap();
+        assert_ne!(first.address(), second.address());
+        let signature = second.sign("ADAMAS");
+        assert!(Wallet::verify("ADAMAS", &signature, &second.public_key));
+        assert!(!Wallet::verify("ADAMAS", &signature, &first.public_key));
     }
-}
\ No newline at end of file
+}
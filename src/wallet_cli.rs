@@ -0,0 +1,571 @@
+// ADAMAS WALLET: gestione chiavi e transazioni da riga di comando.
+// Le chiavi stanno in keystore cifrati (vedi keystore.rs); il nodo si raggiunge via HTTP.
+use adamas_core::address;
+use adamas_core::block::Block;
+use adamas_core::certification::CertificationRecord;
+use adamas_core::keystore::Keystore;
+use adamas_core::mnemonic;
+use adamas_core::merkle::StateProof;
+use adamas_core::multisig::MultisigPolicy;
+use adamas_core::notary::{self, DocumentAnchor, DocumentRecord};
+use adamas_core::offline::{self, UnsignedTransaction};
+use adamas_core::signature::{self, Algorithm};
+use adamas_core::signer::{self, RemoteSigner, Signer};
+use adamas_core::transaction::{Transaction, TransactionKind, ValidityBound};
+use adamas_core::wallet::Wallet;
+use sha3::{Digest, Sha3_256};
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::io::{self, BufRead, Read, Write};
+use std::net::TcpStream;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// Nodo di default: la dashboard locale (vedi DEFAULT_HTTP_PORT in main.rs)
+const DEFAULT_NODE: &str = "127.0.0.1:3000";
+
+// Se presente, evita la richiesta interattiva della passphrase (script, test)
+const PASSPHRASE_ENV: &str = "ADAMAS_PASSPHRASE";
+
+const USAGE: &str = "ADAMAS WALLET
+
+USO: adamas-wallet <comando> [argomenti] [--node host:porta]
+
+  Dove serve firmare, <keystore> può essere anche il socket (o l'indirizzo 127.0.0.1:porta) di un adamas-signer in esecuzione.
+  send, rotate, certify, anchor, build e build-rotation accettano una finestra di validità:
+  --valid-after e --valid-until con height:N (altezza del blocco) oppure time:N (secondi UNIX).
+
+  new <keystore> [--scheme nome]                   nuova chiave + frase di recupero (solo dilithium5)
+  import <keystore> [percorso]                     ricostruisce la chiave dalla frase (default m/0)
+  address <keystore>                               mostra l'indirizzo
+  balance <indirizzo>                              saldo e nonce dal nodo
+  send <keystore> <destinatario> <importo> <fee> [--from indirizzo] [--nonce N] [--out file]
+                                                   firma un trasferimento e lo invia al nodo
+  rotate <keystore> <nuovo keystore> <fee> [--from indirizzo] [--nonce N] [--out file]
+                                                   sostituisce la chiave del conto (indirizzo e storico restano)
+  submit <file>                                    invia una transazione firmata (JSON o testo ADMSTX2)
+  status <id transazione>                          in attesa, inclusa, definitiva o rifiutata (con il motivo)
+
+  Firma offline (chiavi su macchine isolate):
+  build <mittente> <destinatario> <importo> <fee> [--nonce N] [--policy file] [--out file]
+                                                   [online] prepara una transazione non firmata
+                                                   (--policy: conto multisig, serve alla prima spesa)
+  build-rotation <mittente> <nuovo keystore> <fee> [--nonce N] [--policy file] [--out file]
+                                                   [online] prepara una rotazione di chiave non firmata
+  sign-offline <keystore> <file non firmato> [--out file]
+                                                   [offline] firma; senza --out stampa il testo ADMSTX2
+  cosign <keystore> <file firmato>                 [offline] aggiunge una firma a un multisig
+  multisig-address <m> <chiave>... [--out file]    indirizzo e politica di un conto m-di-n
+                                                   (chiavi come keystore o chiave pubblica in hex)
+
+  certify <keystore> <prodotto> <lotto> [operatore] [--location luogo] [--quantity \"N unità\"]
+          [--attach file]... [--meta chiave=valore]... [--fee N] [--from indirizzo] [--nonce N] [--out file]
+                                                   firma un record di certificazione e lo invia al nodo
+  anchor <keystore> <file> [--name nome] [--meta chiave=valore]... [--fee N] [--from indirizzo] [--nonce N] [--out file]
+                                                   notarizza l'impronta SHA3-256 di un documento
+  verify-document <file>                           quando è stato ancorato un documento, con prova di inclusione
+  sign <keystore> <messaggio>                      firma un messaggio di testo (mai una transazione)
+  verify <messaggio> <firma> <chiave_pubblica>     verifica la firma di un messaggio di testo
+  verify-record <file>                             verifica un record di certificazione (transazione firmata)
+  schemes                                          elenca gli schemi di firma supportati";
+
+// Opzioni "--chiave valore" della riga di comando
+type Options = Vec<(String, String)>;
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if let Err(e) = run(args) {
+        eprintln!("❌ {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn run(args: Vec<String>) -> Result<(), Box<dyn Error>> {
+    let (positional, options) = parse_args(args)?;
+    let node = option(&options, "--node").unwrap_or(DEFAULT_NODE).to_string();
+    let command = match positional.first() {
+        Some(command) => command.as_str(),
+        None => {
+            println!("{}", USAGE);
+            return Ok(());
+        }
+    };
+    let arg = |i: usize| -> Result<&str, Box<dyn Error>> {
+        positional.get(i).map(|s| s.as_str()).ok_or_else(|| format!("missing argument\n\n{}", USAGE).into())
+    };
+
+    match command {
+        "new" => {
+            let path = arg(1)?;
+            let scheme = option(&options, "--scheme").unwrap_or(signature::DEFAULT_ALGORITHM.name());
+            let algorithm = Algorithm::from_name(scheme).ok_or_else(|| format!("unknown signature scheme '{}'", scheme))?;
+            let passphrase = new_passphrase()?;
+            // La derivazione da frase esiste solo per Dilithium-5 (vedi mnemonic.rs)
+            let (phrase, wallet) = if algorithm == Algorithm::Dilithium5 {
+                let (phrase, wallet) = Wallet::generate_mnemonic("")?;
+                (Some(phrase), wallet)
+            } else {
+                (None, Wallet::generate(algorithm))
+            };
+            wallet.save_keystore(path, &passphrase)?;
+            println!("🔑 ADDRESS ({}): {}", algorithm.name(), wallet.address());
+            println!("💾 KEYSTORE: {}", path);
+            println!();
+            match phrase {
+                Some(phrase) => {
+                    println!("📝 FRASE DI RECUPERO (scrivila su carta, non verrà mostrata di nuovo):");
+                    println!("{}", phrase);
+                }
+                None => println!("⚠️ Nessuna frase di recupero per {}: conserva una copia del keystore", algorithm.name()),
+            }
+        }
+        "import" => {
+            let path = arg(1)?;
+            let derivation = positional.get(2).map(|s| s.as_str()).unwrap_or(mnemonic::DEFAULT_PATH);
+            let phrase = prompt("Frase di recupero: ")?;
+            let wallet = Wallet::from_mnemonic(&phrase, "", derivation)?;
+            let passphrase = new_passphrase()?;
+            wallet.save_keystore(path, &passphrase)?;
+            println!("🔑 ADDRESS ({}): {}", derivation, wallet.address());
+            println!("💾 KEYSTORE: {}", path);
+        }
+        "address" => {
+            // La chiave pubblica è in chiaro nel keystore: non serve la passphrase
+            println!("{}", Keystore::load(arg(1)?)?.address);
+        }
+        "balance" => {
+            let account = http_get(&node, &format!("/accounts/{}", arg(1)?))?;
+            println!("{}", serde_json::to_string_pretty(&account)?);
+        }
+        "send" => {
+            let wallet = open_signer(arg(1)?)?;
+            let receiver = arg(2)?.to_string();
+            if !address::is_valid(&receiver) {
+                return Err(format!("invalid receiver address: {}", receiver).into());
+            }
+            let amount: u64 = arg(3)?.parse()?;
+            let fee: u64 = arg(4)?.parse()?;
+            // Dopo una rotazione l'indirizzo del conto non è quello della chiave: va indicato con --from
+            let sender = option(&options, "--from").map(|s| s.to_string()).unwrap_or_else(|| wallet.address());
+            let nonce = match option(&options, "--nonce") {
+                Some(nonce) => nonce.parse()?,
+                None => fetch_nonce(&node, &sender)?,
+            };
+            let mut tx = Transaction::unsigned(sender, receiver, amount, fee, nonce);
+            (tx.valid_after, tx.valid_until) = validity_window(&options)?;
+            tx.validate()?;
+            tx.sign(wallet.as_ref())?;
+            output_or_submit(&node, &tx, option(&options, "--out"))?;
+        }
+        "rotate" => {
+            let wallet = open_signer(arg(1)?)?;
+            // Basta la chiave pubblica del nuovo keystore: la passphrase serve solo a chi firmerà dopo
+            let new_key = Keystore::load(arg(2)?)?.public_key;
+            let fee: u64 = arg(3)?.parse()?;
+            let sender = option(&options, "--from").map(|s| s.to_string()).unwrap_or_else(|| wallet.address());
+            let nonce = match option(&options, "--nonce") {
+                Some(nonce) => nonce.parse()?,
+                None => fetch_nonce(&node, &sender)?,
+            };
+            println!("🔄 KEY ROTATION: {} -> chiave di {}", sender, address::from_account_key(&new_key));
+            let mut tx = Transaction::unsigned(sender.clone(), sender, 0, fee, nonce);
+            tx.kind = TransactionKind::KeyRotation { new_key };
+            (tx.valid_after, tx.valid_until) = validity_window(&options)?;
+            tx.validate()?;
+            tx.sign(wallet.as_ref())?;
+            output_or_submit(&node, &tx, option(&options, "--out"))?;
+        }
+        "status" => {
+            let (status, body) = http_request(&node, "GET", &format!("/tx/{}", arg(1)?), "")?;
+            let receipt: serde_json::Value = serde_json::from_str(&body)?;
+            println!("{}", serde_json::to_string_pretty(&receipt)?);
+            if status != 200 {
+                return Err("transaction unknown to the node".into());
+            }
+        }
+        "submit" => {
+            let tx = offline::parse_signed(&fs::read_to_string(arg(1)?)?)?;
+            submit(&node, &tx)?;
+        }
+        "build" => {
+            let sender = arg(1)?.to_string();
+            let receiver = arg(2)?.to_string();
+            if !address::is_valid(&sender) || !address::is_valid(&receiver) {
+                return Err("invalid sender or receiver address".into());
+            }
+            let amount: u64 = arg(3)?.parse()?;
+            let fee: u64 = arg(4)?.parse()?;
+            let (nonce, account_key) = fetch_account(&node, &sender, option(&options, "--nonce"))?;
+            let mut unsigned = with_policy(UnsignedTransaction::new(sender, receiver, amount, fee, nonce, account_key), &options)?;
+            (unsigned.valid_after, unsigned.valid_until) = validity_window(&options)?;
+            save_unsigned(&unsigned, option(&options, "--out"))?;
+        }
+        "build-rotation" => {
+            let sender = arg(1)?.to_string();
+            if !address::is_valid(&sender) {
+                return Err(format!("invalid sender address: {}", sender).into());
+            }
+            let new_key = Keystore::load(arg(2)?)?.public_key;
+            let fee: u64 = arg(3)?.parse()?;
+            let (nonce, account_key) = fetch_account(&node, &sender, option(&options, "--nonce"))?;
+            let mut unsigned = with_policy(UnsignedTransaction::new_key_rotation(sender, new_key, fee, nonce, account_key), &options)?;
+            (unsigned.valid_after, unsigned.valid_until) = validity_window(&options)?;
+            save_unsigned(&unsigned, option(&options, "--out"))?;
+        }
+        "sign-offline" => {
+            let wallet = open_signer(arg(1)?)?;
+            let unsigned = UnsignedTransaction::load(arg(2)?)?;
+            println!("📝 {} -> {} ({} + fee {}, nonce {})", unsigned.sender, unsigned.receiver, unsigned.amount, unsigned.fee, unsigned.nonce);
+            if unsigned.valid_after.is_some() || unsigned.valid_until.is_some() {
+                let encode = |bound: Option<ValidityBound>| bound.map(|bound| bound.encode()).unwrap_or_else(|| "-".to_string());
+                println!("⏱️ VALIDITY: da {} a {}", encode(unsigned.valid_after), encode(unsigned.valid_until));
+            }
+            let tx = unsigned.sign(wallet.as_ref())?;
+            match option(&options, "--out") {
+                Some(out) => {
+                    fs::write(out, serde_json::to_string_pretty(&tx)?)?;
+                    println!("💾 SIGNED TX: {}", out);
+                }
+                None => print_text(&offline::signed_to_text(&tx)?),
+            }
+        }
+        "cosign" => {
+            let wallet = open_signer(arg(1)?)?;
+            let path = arg(2)?;
+            let mut tx = offline::parse_signed(&fs::read_to_string(path)?)?;
+            let policy = MultisigPolicy::decode(&tx.public_key).ok_or("not a multisig transaction")?;
+            tx.add_signature(&policy, wallet.as_ref())?;
+            fs::write(path, serde_json::to_string_pretty(&tx)?)?;
+            println!("✍️ SIGNATURES: {}/{} ({})", tx.signatures.len(), policy.threshold, path);
+        }
+        "multisig-address" => {
+            let threshold: usize = arg(1)?.parse()?;
+            // La chiave pubblica è in chiaro nel keystore: non serve la passphrase
+            let keys = positional[2..].iter()
+                .map(|key| if Path::new(key).is_file() { Keystore::load(key).map(|keystore| keystore.public_key) } else { Ok(key.clone()) })
+                .collect::<Result<Vec<String>, _>>()?;
+            let policy = MultisigPolicy::new(threshold, keys)?;
+            println!("🔐 MULTISIG {}-di-{}: {}", policy.threshold, policy.public_keys.len(), policy.address());
+            match option(&options, "--out") {
+                Some(out) => {
+                    fs::write(out, policy.encode())?;
+                    println!("💾 POLICY: {} (da passare a build --policy)", out);
+                }
+                None => println!("{}", policy.encode()),
+            }
+        }
+        "certify" => {
+            let wallet = open_signer(arg(1)?)?;
+            let operator = positional.get(4).map(|s| s.as_str()).unwrap_or("ADMIN").to_string();
+            let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+            let mut record = CertificationRecord::new(arg(2)?.to_string(), arg(3)?.to_string(), operator, timestamp);
+            record.location = option(&options, "--location").unwrap_or_default().to_string();
+            if let Some(quantity) = option(&options, "--quantity") {
+                let (amount, unit) = quantity.split_once(' ').unwrap_or((quantity, ""));
+                record.quantity = amount.parse()?;
+                record.unit = unit.trim().to_string();
+            }
+            // Degli allegati va on-chain solo l'impronta
+            for path in all_options(&options, "--attach") {
+                record.attachments.push(hex::encode(Sha3_256::digest(fs::read(path)?)));
+            }
+            for entry in all_options(&options, "--meta") {
+                let (key, value) = entry.split_once('=').ok_or_else(|| format!("metadata must be key=value: {}", entry))?;
+                record.metadata.insert(key.to_string(), value.to_string());
+            }
+            let kind = TransactionKind::CertifyRecord(record);
+            let fee: u64 = option(&options, "--fee").unwrap_or("0").parse()?;
+            let sender = option(&options, "--from").map(|s| s.to_string()).unwrap_or_else(|| wallet.address());
+            let nonce = match option(&options, "--nonce") {
+                Some(nonce) => nonce.parse()?,
+                None => fetch_nonce(&node, &sender)?,
+            };
+            // Il record è una transazione verso sé stessi: la firma del mittente lo rende verificabile da chiunque
+            let mut tx = Transaction::unsigned(sender.clone(), sender, 0, fee, nonce);
+            tx.kind = kind;
+            (tx.valid_after, tx.valid_until) = validity_window(&options)?;
+            tx.validate()?;
+            tx.sign(wallet.as_ref())?;
+            output_or_submit(&node, &tx, option(&options, "--out"))?;
+        }
+        "anchor" => {
+            let wallet = open_signer(arg(1)?)?;
+            let path = arg(2)?;
+            // On-chain va solo l'impronta: il file non lascia questa macchina
+            let mut document = DocumentRecord::new(notary::hash_file(&fs::read(path)?));
+            document.name = option(&options, "--name")
+                .map(|name| name.to_string())
+                .unwrap_or_else(|| Path::new(path).file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default());
+            for entry in all_options(&options, "--meta") {
+                let (key, value) = entry.split_once('=').ok_or_else(|| format!("metadata must be key=value: {}", entry))?;
+                document.metadata.insert(key.to_string(), value.to_string());
+            }
+            println!("📌 DOCUMENT: {} ({})", document.hash, document.name);
+            let fee: u64 = option(&options, "--fee").unwrap_or("0").parse()?;
+            let sender = option(&options, "--from").map(|s| s.to_string()).unwrap_or_else(|| wallet.address());
+            let nonce = match option(&options, "--nonce") {
+                Some(nonce) => nonce.parse()?,
+                None => fetch_nonce(&node, &sender)?,
+            };
+            let mut tx = Transaction::unsigned(sender.clone(), sender, 0, fee, nonce);
+            tx.kind = TransactionKind::AnchorDocument(document);
+            (tx.valid_after, tx.valid_until) = validity_window(&options)?;
+            tx.validate()?;
+            tx.sign(wallet.as_ref())?;
+            output_or_submit(&node, &tx, option(&options, "--out"))?;
+        }
+        "verify-document" => {
+            let hash = notary::hash_file(&fs::read(arg(1)?)?);
+            let report = http_get(&node, &format!("/documents/{}", hash))?;
+            let anchor: Option<DocumentAnchor> = serde_json::from_value(report["anchor"].clone())?;
+            let proof: StateProof = serde_json::from_value(report["proof"].clone())?;
+            match &anchor {
+                Some(anchor) => println!("📌 ANCHORED: blocco #{} ({} conferme), tx {} da {}",
+                    anchor.block_index, report["confirmations"], anchor.tx_id, anchor.owner),
+                None => println!("❔ NOT ANCHORED: {}", hash),
+            }
+            // La radice della prova deve essere lo state_root di un blocco vero (hash e firma del produttore),
+            // non un valore qualsiasi scelto dal nodo
+            let proof_height = report["proof_height"].as_u64().ok_or("missing proof_height")?;
+            let block: Block = serde_json::from_value(http_get(&node, &format!("/block/{}", proof_height))?)?;
+            let block_valid = block.index == proof_height
+                && block.hash == report["proof_block_hash"].as_str().unwrap_or_default()
+                && block.hash == block.calculate_hash()
+                && (block.index == 0 || block.verify_signature())
+                && block.state_root == proof.root;
+            if !block_valid {
+                return Err(format!("INVALID PROOF: root {} is not the state root of block #{}", proof.root, proof_height).into());
+            }
+            println!("🌳 STATE ROOT: {} (blocco #{} {})", proof.root, block.index, block.hash);
+            // La prova deve riguardare questa impronta e confermare proprio ciò che il nodo dichiara
+            let anchored_before = anchor.as_ref().is_none_or(|anchor| anchor.block_index <= block.index);
+            let expected = anchor.map(|anchor| anchor.leaf()).unwrap_or_default();
+            let valid = anchored_before && proof.key == format!("document/{}", hash) && proof.value == hex::encode(expected) && proof.verify();
+            if !valid {
+                return Err("INVALID PROOF".into());
+            }
+            println!("✅ VALID PROOF (confrontare l'hash del blocco con un altro nodo per non fidarsi solo di questo)");
+        }
+        "sign" => {
+            let wallet = open_signer(arg(1)?)?;
+            println!("{}", wallet.sign_message(&signer::text_message(arg(2)?))?);
+        }
+        "verify" => {
+            report(Wallet::verify(&signer::text_message(arg(1)?), arg(2)?, arg(3)?))?;
+        }
+        "verify-record" => {
+            let tx = offline::parse_signed(&fs::read_to_string(arg(1)?)?)?;
+            match &tx.kind {
+                TransactionKind::CertifyRecord(record) => {
+                    println!("📄 {} ({})", serde_json::to_string_pretty(record)?, tx.sender);
+                }
+                other => return Err(format!("not a certification record ({})", other.name()).into()),
+            }
+            // Offline si può controllare solo la chiave che deriva dall'indirizzo (conti mai ruotati)
+            report(tx.validate().is_ok() && tx.verify(None))?;
+        }
+        "schemes" => {
+            for algorithm in Algorithm::ALL {
+                let default = if algorithm == signature::DEFAULT_ALGORITHM { " (default)" } else { "" };
+                println!("{:<18} id 0x{:02x}{}", algorithm.name(), algorithm.id(), default);
+            }
+        }
+        _ => return Err(format!("unknown command '{}'\n\n{}", command, USAGE).into()),
+    }
+    Ok(())
+}
+
+// Separa gli argomenti posizionali dalle opzioni "--chiave valore"
+fn parse_args(args: Vec<String>) -> Result<(Vec<String>, Options), Box<dyn Error>> {
+    let mut positional = Vec::new();
+    let mut options = Vec::new();
+    let mut iter = args.into_iter();
+    while let Some(arg) = iter.next() {
+        if arg.starts_with("--") {
+            let value = iter.next().ok_or_else(|| format!("missing value for {}", arg))?;
+            options.push((arg, value));
+        } else {
+            positional.push(arg);
+        }
+    }
+    Ok((positional, options))
+}
+
+fn option<'a>(options: &'a Options, name: &str) -> Option<&'a str> {
+    options.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str())
+}
+
+// Opzioni ripetibili (es. più --attach)
+fn all_options<'a>(options: &'a Options, name: &'a str) -> impl Iterator<Item = &'a str> {
+    options.iter().filter(move |(key, _)| key == name).map(|(_, value)| value.as_str())
+}
+
+// --valid-after / --valid-until: limiti della finestra di validità (height:N oppure time:N)
+fn validity_window(options: &Options) -> Result<(Option<ValidityBound>, Option<ValidityBound>), Box<dyn Error>> {
+    let bound = |name| option(options, name).map(ValidityBound::parse).transpose();
+    Ok((bound("--valid-after")?, bound("--valid-until")?))
+}
+
+fn report(valid: bool) -> Result<(), Box<dyn Error>> {
+    if !valid {
+        return Err("INVALID SIGNATURE".into());
+    }
+    println!("✅ VALID SIGNATURE");
+    Ok(())
+}
+
+fn prompt(label: &str) -> Result<String, Box<dyn Error>> {
+    eprint!("{}", label);
+    io::stderr().flush()?;
+    let mut line = String::new();
+    io::stdin().lock().read_line(&mut line)?;
+    Ok(line.trim().to_string())
+}
+
+fn passphrase() -> Result<String, Box<dyn Error>> {
+    match env::var(PASSPHRASE_ENV) {
+        Ok(passphrase) => Ok(passphrase),
+        Err(_) => prompt("Passphrase: "),
+    }
+}
+
+fn new_passphrase() -> Result<String, Box<dyn Error>> {
+    if let Ok(passphrase) = env::var(PASSPHRASE_ENV) {
+        return Ok(passphrase);
+    }
+    let passphrase = prompt("Nuova passphrase: ")?;
+    if prompt("Ripeti passphrase: ")? != passphrase {
+        return Err("passphrases do not match".into());
+    }
+    Ok(passphrase)
+}
+
+fn open_keystore(path: &str) -> Result<Wallet, Box<dyn Error>> {
+    Wallet::load_keystore(path, &passphrase()?)
+}
+
+// Keystore cifrato oppure adamas-signer (socket o indirizzo TCP locale): nel secondo caso la chiave resta nell'altro processo
+fn open_signer(path: &str) -> Result<Box<dyn Signer>, Box<dyn Error>> {
+    if signer::is_endpoint(path) {
+        return Ok(Box::new(RemoteSigner::connect(path)?));
+    }
+    Ok(Box::new(open_keystore(path)?))
+}
+
+fn fetch_nonce(node: &str, address: &str) -> Result<u64, Box<dyn Error>> {
+    let account = http_get(node, &format!("/accounts/{}", address))?;
+    account["nonce"].as_u64().ok_or_else(|| "the node did not return a nonce".into())
+}
+
+// Nonce (o quello passato con --nonce) e chiave registrata del conto, per preparare una transazione offline
+fn fetch_account(node: &str, address: &str, nonce: Option<&str>) -> Result<(u64, String), Box<dyn Error>> {
+    let account = http_get(node, &format!("/accounts/{}", address))?;
+    let nonce = match nonce {
+        Some(nonce) => nonce.parse()?,
+        None => account["nonce"].as_u64().ok_or("the node did not return a nonce")?,
+    };
+    let account_key = account["public_key"].as_str().unwrap_or_default().to_string();
+    Ok((nonce, account_key))
+}
+
+// --policy file: politica del conto multisig (scritta da multisig-address)
+fn with_policy(unsigned: UnsignedTransaction, options: &Options) -> Result<UnsignedTransaction, Box<dyn Error>> {
+    match option(options, "--policy") {
+        Some(path) => {
+            let policy = MultisigPolicy::decode(fs::read_to_string(path)?.trim()).ok_or("not a multisig policy")?;
+            unsigned.with_policy(&policy)
+        }
+        None => Ok(unsigned),
+    }
+}
+
+fn save_unsigned(unsigned: &UnsignedTransaction, out: Option<&str>) -> Result<(), Box<dyn Error>> {
+    if let Some(out) = out {
+        unsigned.save(out)?;
+        println!("💾 UNSIGNED TX (nonce {}): {}", unsigned.nonce, out);
+    }
+    print_text(&unsigned.to_text()?);
+    Ok(())
+}
+
+// Testo da portare sull'altra macchina: se non sta in un QR code va spostato come file (--out)
+fn print_text(text: &str) {
+    println!("{}", text);
+    if !offline::fits_qr(text) {
+        eprintln!("⚠️ {} caratteri: troppi per un QR code (max {}), trasferirlo come file con --out", text.len(), offline::QR_ALPHANUMERIC_CAPACITY);
+    }
+}
+
+fn output_or_submit(node: &str, tx: &Transaction, out: Option<&str>) -> Result<(), Box<dyn Error>> {
+    match out {
+        Some(out) => {
+            fs::write(out, serde_json::to_string_pretty(tx)?)?;
+            println!("💾 SIGNED TX (nonce {}): {}", tx.nonce, out);
+            Ok(())
+        }
+        None => submit(node, tx),
+    }
+}
+
+fn submit(node: &str, tx: &Transaction) -> Result<(), Box<dyn Error>> {
+    let (status, body) = http_request(node, "POST", "/transactions", &serde_json::to_string(tx)?)?;
+    if status != 200 {
+        let reason = serde_json::from_str::<serde_json::Value>(&body).ok()
+            .and_then(|response| response["reason"].as_str().map(|reason| reason.to_string()))
+            .unwrap_or(body);
+        return Err(format!("transaction {} rejected by the node (HTTP {}): {}", tx.id(), status, reason).into());
+    }
+    println!("📨 TX SUBMITTED: {} -> {} ({} + fee {}, nonce {})", tx.sender, tx.receiver, tx.amount, tx.fee, tx.nonce);
+    println!("🧾 TX ID: {}", tx.id());
+    Ok(())
+}
+
+fn http_get(node: &str, path: &str) -> Result<serde_json::Value, Box<dyn Error>> {
+    let (status, body) = http_request(node, "GET", path, "")?;
+    if status != 200 {
+        // Il nodo spiega le richieste rifiutate nel campo "error"
+        return match serde_json::from_str::<serde_json::Value>(&body).ok().and_then(|reply| reply["error"].as_str().map(String::from)) {
+            Some(reason) => Err(format!("GET {} failed (HTTP {}): {}", path, status, reason).into()),
+            None => Err(format!("GET {} failed (HTTP {})", path, status).into()),
+        };
+    }
+    Ok(serde_json::from_str(&body)?)
+}
+
+// Client HTTP minimo, speculare al server del nodo: una richiesta per connessione
+fn http_request(node: &str, method: &str, path: &str, body: &str) -> Result<(u16, String), Box<dyn Error>> {
+    let mut stream = TcpStream::connect(node).map_err(|e| format!("cannot reach node {}: {}", node, e))?;
+    stream.set_read_timeout(Some(Duration::from_secs(30)))?;
+    let request = format!(
+        "{} {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        method, path, node, body.len(), body
+    );
+    stream.write_all(request.as_bytes())?;
+
+    // Si legge fino a Content-Length (o fino alla chiusura della connessione)
+    let mut data = Vec::new();
+    let mut buffer = [0; 4096];
+    loop {
+        let n = stream.read(&mut buffer)?;
+        if n == 0 {
+            break;
+        }
+        data.extend_from_slice(&buffer[..n]);
+        if let Some(end) = data.windows(4).position(|w| w == b"\r\n\r\n") {
+            let head = String::from_utf8_lossy(&data[..end]).to_lowercase();
+            let content_length = head.lines()
+                .find_map(|line| line.strip_prefix("content-length:"))
+                .and_then(|value| value.trim().parse::<usize>().ok())
+                .unwrap_or(0);
+            if data.len() >= end + 4 + content_length {
+                break;
+            }
+        }
+    }
+
+    let response = String::from_utf8_lossy(&data).to_string();
+    let (head, body) = response.split_once("\r\n\r\n").ok_or("malformed response from node")?;
+    let status = head.split_whitespace().nth(1).and_then(|code| code.parse().ok()).ok_or("malformed response from node")?;
+    Ok((status, body.to_string()))
+}